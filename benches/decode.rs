@@ -0,0 +1,92 @@
+//! 解码一个典型的嵌套消息: `cargo bench --bench decode`
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::hint::black_box;
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+    #[serde(rename = "0")]
+    version: i16,
+    #[serde(rename = "1")]
+    request_id: i32,
+    #[serde(rename = "2")]
+    servant: String,
+    #[serde(rename = "3")]
+    func: String,
+    #[serde(rename = "4")]
+    timeout: i32,
+    #[serde(rename = "5")]
+    context: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Item {
+    #[serde(rename = "0")]
+    id: u64,
+    #[serde(rename = "1")]
+    name: String,
+    #[serde(rename = "2")]
+    price: f64,
+    #[serde(rename = "3")]
+    tags: Vec<String>,
+    #[serde(rename = "4", default)]
+    stock: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Message {
+    #[serde(rename = "0")]
+    header: Header,
+    #[serde(rename = "1")]
+    items: Vec<Item>,
+    #[serde(rename = "2", with = "serde_bytes")]
+    payload: Vec<u8>,
+    #[serde(rename = "3")]
+    counters: Vec<i64>,
+}
+
+fn message() -> Message {
+    Message {
+        header: Header {
+            version: 3,
+            request_id: 123_456,
+            servant: "Test.HelloServer.HelloObj".into(),
+            func: "queryItems".into(),
+            timeout: 3000,
+            context: (0..8)
+                .map(|i| (format!("key{}", i), format!("value{}", i)))
+                .collect(),
+        },
+        items: (0..64)
+            .map(|i| Item {
+                id: i * 1_000_003,
+                name: format!("item-{}", i),
+                price: i as f64 * 1.25,
+                tags: vec!["a".into(), "bb".into(), "ccc".into()],
+                stock: (i % 2 == 0).then_some(i as i32),
+            })
+            .collect(),
+        payload: vec![0x5A; 512],
+        counters: (0..128).map(|i| i * i * 997 - 5000).collect(),
+    }
+}
+
+fn decode(c: &mut Criterion) {
+    let buf = serde_jce::to_vec(&message()).unwrap();
+    let mut group = c.benchmark_group("decode");
+    group.throughput(Throughput::Bytes(buf.len() as u64));
+    group.bench_function("from_slice", |b| {
+        b.iter(|| serde_jce::from_slice::<Message>(black_box(&buf)).unwrap())
+    });
+    group.bench_function("from_reader", |b| {
+        b.iter(|| serde_jce::from_reader::<Message, _>(black_box(&buf[..])).unwrap())
+    });
+    group.bench_function("to_value", |b| {
+        b.iter(|| serde_jce::from_slice_to_value(black_box(&buf)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, decode);
+criterion_main!(benches);
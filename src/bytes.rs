@@ -0,0 +1,54 @@
+//! 与`bytes`的`Buf`/`BufMut`配合使用，直接从`Bytes`解码、编码到`BytesMut`
+use ::bytes::{Buf, BufMut};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::{Deserializer, Result, Serializer};
+
+/// 从`Buf`中解码，读取的数据会从`buf`中消耗掉
+pub fn from_buf<T, B>(buf: B) -> Result<T>
+where
+    T: DeserializeOwned,
+    B: Buf,
+{
+    let mut deserializer = Deserializer::new(buf.reader());
+    T::deserialize(&mut deserializer)
+}
+
+/// 序列化并追加到`BufMut`
+pub fn to_buf_mut<T, B>(buf: &mut B, value: &T) -> Result<()>
+where
+    T: Serialize,
+    B: BufMut,
+{
+    let mut serializer = Serializer::new(buf.writer());
+    value.serialize(&mut serializer)
+}
+
+#[test]
+fn test_bytes_round_trip() -> Result<()> {
+    use ::bytes::{Bytes, BytesMut};
+
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Packet {
+        #[serde(rename = "0")]
+        seq: u32,
+        #[serde(rename = "1")]
+        body: String,
+        #[serde(rename = "2", with = "serde_bytes")]
+        payload: Vec<u8>,
+    }
+
+    let value = Packet {
+        seq: 42,
+        body: "hello".into(),
+        payload: vec![1, 2, 3],
+    };
+    let mut buf = BytesMut::new();
+    to_buf_mut(&mut buf, &value)?;
+    assert_eq!(&buf[..], &crate::to_vec(&value)?[..]);
+
+    let bytes: Bytes = buf.freeze();
+    assert_eq!(from_buf::<Packet, _>(bytes)?, value);
+    Ok(())
+}
@@ -0,0 +1,49 @@
+//! `chrono`时间类型的编解码
+
+/// `DateTime<Utc>`写为epoch毫秒数的整型（对应JCE的long），
+/// 用法: `#[serde(rename = "1", with = "serde_jce::chrono::timestamp_millis")]`
+pub mod timestamp_millis {
+    use ::chrono::{DateTime, Utc};
+    use serde::{Deserialize, Deserializer, Serializer, de};
+
+    pub fn serialize<S>(time: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_i64(time.timestamp_millis())
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let millis = i64::deserialize(deserializer)?;
+        DateTime::from_timestamp_millis(millis)
+            .ok_or_else(|| de::Error::custom(format!("Timestamp {} ms is out of range", millis)))
+    }
+}
+
+#[test]
+fn test_timestamp_millis() -> crate::Result<()> {
+    use ::chrono::{DateTime, TimeZone, Utc};
+
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Event {
+        #[serde(rename = "1", with = "timestamp_millis")]
+        at: DateTime<Utc>,
+    }
+
+    let at = Utc.with_ymd_and_hms(2024, 2, 29, 12, 30, 45).unwrap()
+        + ::chrono::Duration::milliseconds(123);
+    let serialized = crate::to_vec(&Event { at })?;
+    // tag 1 Int64
+    assert_eq!(serialized[0], 0x13);
+    assert_eq!(serialized[1..], 1_709_209_845_123i64.to_be_bytes());
+    assert_eq!(crate::from_slice::<Event>(&serialized)?, Event { at });
+
+    let mut out_of_range = vec![0x13];
+    out_of_range.extend_from_slice(&i64::MAX.to_be_bytes());
+    let err = crate::from_slice::<Event>(&out_of_range).unwrap_err();
+    assert!(err.to_string().contains("out of range"), "{}", err);
+    Ok(())
+}
@@ -0,0 +1,240 @@
+//! 匹配schema差异的字段转换，配合`#[serde(with = "...")]`使用
+
+/// `bool`写为整型（`false`为Zero，`true`为Int1的1），解码时只接受0和1
+pub mod bool_as_int {
+    use serde::{Deserialize, Deserializer, Serializer, de};
+
+    pub fn serialize<S>(v: &bool, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_u8(*v as u8)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<bool, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match i64::deserialize(deserializer)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            v => Err(de::Error::custom(format!(
+                "Expected 0 or 1 for bool, got {}",
+                v
+            ))),
+        }
+    }
+}
+
+/// 数字形式的`String`（如ID）写为整型，编码时解析失败报错
+pub mod string_as_number {
+    use serde::{Deserialize, Deserializer, Serializer, ser};
+
+    pub fn serialize<S>(v: &str, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let n = v
+            .parse::<i64>()
+            .map_err(|_| ser::Error::custom(format!("{:?} is not an integer", v)))?;
+        serializer.serialize_i64(n)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<String, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(i64::deserialize(deserializer)?.to_string())
+    }
+}
+
+/// 数值按十进制文本写为字符串，适用于任何实现了`Display`与`FromStr`的类型
+pub mod number_as_string {
+    use serde::{Deserialize, Deserializer, Serializer, de};
+    use std::fmt::Display;
+    use std::str::FromStr;
+
+    pub fn serialize<T, S>(v: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Display,
+        S: Serializer,
+    {
+        serializer.collect_str(v)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse()
+            .map_err(|e| de::Error::custom(format!("Invalid number {:?}: {}", s, e)))
+    }
+}
+
+/// 配合`#[serde(flatten, deserialize_with = "serde_jce::compat::extra_tags::deserialize")]`
+/// 把结构体中未声明的tag收集到`BTreeMap<u8, Value>`
+///
+/// flatten时字段按十进制tag匹配，与之一起使用的结构体字段名不能写为十六进制
+pub mod extra_tags {
+    use crate::de::Value;
+    use serde::{Deserializer, de};
+    use std::collections::BTreeMap;
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<BTreeMap<u8, Value>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct TagsVisitor;
+
+        impl<'de> de::Visitor<'de> for TagsVisitor {
+            type Value = BTreeMap<u8, Value>;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("struct fields keyed by tag")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: de::MapAccess<'de>,
+            {
+                let mut tags = BTreeMap::new();
+                while let Some((key, value)) = map.next_entry::<String, Value>()? {
+                    let tag = crate::raw::parse_tag(&key).ok_or_else(|| {
+                        de::Error::custom(format!("Field name {} is not a valid JCE tag", key))
+                    })?;
+                    tags.insert(tag, value);
+                }
+                Ok(tags)
+            }
+        }
+
+        deserializer.deserialize_map(TagsVisitor)
+    }
+}
+
+/// 任意长度的数组`[T; N]`写为list（serde自身只支持长度不超过32的数组），解码时长度必须为N
+pub mod array {
+    use serde::ser::SerializeTuple;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+    use std::marker::PhantomData;
+
+    pub fn serialize<T, S, const N: usize>(v: &[T; N], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Serialize,
+        S: Serializer,
+    {
+        let mut tuple = serializer.serialize_tuple(N)?;
+        for element in v {
+            tuple.serialize_element(element)?;
+        }
+        tuple.end()
+    }
+
+    pub fn deserialize<'de, T, D, const N: usize>(deserializer: D) -> Result<[T; N], D::Error>
+    where
+        T: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_tuple(N, ArrayVisitor(PhantomData))
+    }
+
+    struct ArrayVisitor<T, const N: usize>(PhantomData<T>);
+
+    impl<'de, T: Deserialize<'de>, const N: usize> de::Visitor<'de> for ArrayVisitor<T, N> {
+        type Value = [T; N];
+
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "a list of {} elements", N)
+        }
+
+        fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<[T; N], A::Error> {
+            let mut elements = Vec::with_capacity(N);
+            while let Some(element) = seq.next_element()? {
+                elements.push(element);
+            }
+            let len = elements.len();
+            elements
+                .try_into()
+                .map_err(|_| de::Error::invalid_length(len, &self))
+        }
+    }
+}
+
+#[test]
+fn test_compat_helpers() -> crate::Result<()> {
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Legacy {
+        #[serde(rename = "0", with = "bool_as_int")]
+        enabled: bool,
+        #[serde(rename = "1", with = "string_as_number")]
+        uid: String,
+        #[serde(rename = "2", with = "number_as_string")]
+        price: f64,
+        #[serde(rename = "3", with = "number_as_string")]
+        count: u32,
+    }
+
+    let legacy = Legacy {
+        enabled: true,
+        uid: "-1234567890123".into(),
+        price: 9.5,
+        count: 3,
+    };
+    let serialized = crate::to_vec(&legacy)?;
+    let fields = crate::from_slice_to_value(&serialized)?;
+    assert_eq!(fields[&0].type_name(), "Int1");
+    assert_eq!(fields[&1].as_i64(), Some(-1_234_567_890_123));
+    assert!(matches!(&fields[&2], crate::de::Value::String(s) if s == "9.5"));
+    assert!(matches!(&fields[&3], crate::de::Value::String(s) if s == "3"));
+    assert_eq!(crate::from_slice::<Legacy>(&serialized)?, legacy);
+
+    let disabled = Legacy {
+        enabled: false,
+        ..legacy
+    };
+    let serialized = crate::to_vec(&disabled)?;
+    assert_eq!(serialized[0], 0x0C);
+    assert_eq!(crate::from_slice::<Legacy>(&serialized)?, disabled);
+
+    // 非0/1的整型不能解码为bool
+    let mut bad = serialized.clone();
+    bad.splice(0..1, [0x00, 0x02]);
+    assert!(crate::from_slice::<Legacy>(&bad).is_err());
+
+    let not_numeric = Legacy {
+        uid: "abc".into(),
+        ..disabled
+    };
+    assert!(crate::to_vec(&not_numeric).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_large_array() -> crate::Result<()> {
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Table {
+        #[serde(rename = "0", with = "array")]
+        entries: [u16; 300],
+        #[serde(rename = "1", with = "array")]
+        bytes: [u8; 256],
+    }
+
+    let mut value = Table {
+        entries: [0; 300],
+        bytes: [0; 256],
+    };
+    for (i, v) in value.entries.iter_mut().enumerate() {
+        *v = (i * 200) as u16;
+    }
+    for (i, v) in value.bytes.iter_mut().enumerate() {
+        *v = i as u8;
+    }
+    let serialized = crate::to_vec(&value)?;
+    // tag 0 list, 长度300写为Int2
+    assert_eq!(&serialized[..4], &[0x09, 0x01, 0x01, 0x2C]);
+    assert_eq!(crate::from_slice::<Table>(&serialized)?, value);
+    Ok(())
+}
@@ -1,7 +1,9 @@
 use crate::error::{Error, Result};
+use crate::raw;
+use crate::read::{JceInput, Rewind, SliceReader};
 use serde::de;
 use serde::de::DeserializeSeed;
-use std::io::Read;
+use std::borrow::Cow;
 
 #[derive(Debug, Clone)]
 pub enum Value {
@@ -19,21 +21,761 @@ pub enum Value {
     Zero,
 }
 
+impl Value {
+    /// 该值在线上对应的JCE类型
+    pub fn jce_type(&self) -> u8 {
+        match self {
+            Value::Byte(_) => 0,
+            Value::Int16(_) => 1,
+            Value::Int32(_) => 2,
+            Value::Int64(_) => 3,
+            Value::Float(_) => 4,
+            Value::Double(_) => 5,
+            Value::String(s) if s.len() <= 0xFF => 6,
+            Value::String(_) => 7,
+            Value::Map(_) => 8,
+            Value::List(_) => 9,
+            Value::Struct(_) => 10,
+            Value::Zero => 12,
+            Value::Bytes(_) => 13,
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        type_name(self.jce_type())
+    }
+
+    /// 整型的数值，`Byte`按有符号的Int1解释，`Zero`为0
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Byte(v) => Some(*v as i8 as i64),
+            Value::Int16(v) => Some(*v as i64),
+            Value::Int32(v) => Some(*v as i64),
+            Value::Int64(v) => Some(*v),
+            Value::Zero => Some(0),
+            _ => None,
+        }
+    }
+
+    /// 规范化:
+    /// - 整型改用能容纳其数值的最短类型，0统一为`Zero`（与最短编码一致）
+    /// - map条目按key的编码字节排序
+    /// - 递归处理map、list与struct，其余类型不变
+    pub fn normalize(self) -> Value {
+        if let Some(v) = self.as_i64() {
+            return match v {
+                0 => Value::Zero,
+                v if i8::try_from(v).is_ok() => Value::Byte(v as i8 as u8),
+                v if i16::try_from(v).is_ok() => Value::Int16(v as i16),
+                v if i32::try_from(v).is_ok() => Value::Int32(v as i32),
+                v => Value::Int64(v),
+            };
+        }
+        match self {
+            Value::Map(entries) => {
+                let mut entries: Vec<(Vec<u8>, Value, Value)> = entries
+                    .into_iter()
+                    .map(|(k, v)| {
+                        let k = k.normalize();
+                        let mut key = Vec::new();
+                        // 写入Vec不会失败
+                        let _ = raw::write_value(&mut key, 0, &k);
+                        (key, k, v.normalize())
+                    })
+                    .collect();
+                entries.sort_by(|a, b| a.0.cmp(&b.0));
+                Value::Map(entries.into_iter().map(|(_, k, v)| (k, v)).collect())
+            }
+            Value::List(items) => Value::List(items.into_iter().map(Value::normalize).collect()),
+            Value::Struct(fields) => Value::Struct(
+                fields
+                    .into_iter()
+                    .map(|(tag, v)| (tag, v.normalize()))
+                    .collect(),
+            ),
+            v => v,
+        }
+    }
+
+    /// 规范化后的编码，相等的值得到相同的字节；`Struct`写为顶层结构体体，其余值以tag 0写入
+    pub fn canonical_bytes(&self) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        match self.clone().normalize() {
+            Value::Struct(fields) => raw::write_fields(&mut buf, &fields)?,
+            v => raw::write_value(&mut buf, 0, &v)?,
+        }
+        Ok(buf)
+    }
+
+    /// 对`Struct`的每个直接字段调用`f(tag, 值)`，可就地修改；其余类型不做处理
+    pub fn map_fields(&mut self, mut f: impl FnMut(u8, &mut Value)) {
+        if let Value::Struct(fields) = self {
+            for (tag, v) in fields.iter_mut() {
+                f(*tag, v);
+            }
+        }
+    }
+
+    /// 先序遍历该值及其包含的所有值（map的key与value、list元素、struct字段），可就地修改
+    pub fn visit_mut(&mut self, mut f: impl FnMut(&mut Value)) {
+        self.visit_mut_dyn(&mut f);
+    }
+
+    fn visit_mut_dyn(&mut self, f: &mut dyn FnMut(&mut Value)) {
+        f(self);
+        match self {
+            Value::Map(entries) => {
+                for (k, v) in entries {
+                    k.visit_mut_dyn(f);
+                    v.visit_mut_dyn(f);
+                }
+            }
+            Value::List(items) => items.iter_mut().for_each(|v| v.visit_mut_dyn(f)),
+            Value::Struct(fields) => fields.values_mut().for_each(|v| v.visit_mut_dyn(f)),
+            _ => {}
+        }
+    }
+
+    /// 类似JSON的文本形式，用于日志打印: struct的tag作为key，字节数组写为十六进制字符串，
+    /// map中非字符串的key写为其文本形式的字符串，非有限的浮点数写为`null`
+    pub fn to_json_like_string(&self) -> String {
+        let mut out = String::new();
+        self.write_json_like(&mut out);
+        out
+    }
+
+    fn write_json_like(&self, out: &mut String) {
+        use std::fmt::Write;
+        // 写入String不会失败
+        match self {
+            Value::Float(v) if v.is_finite() => {
+                let _ = write!(out, "{}", v);
+            }
+            Value::Double(v) if v.is_finite() => {
+                let _ = write!(out, "{}", v);
+            }
+            Value::Float(_) | Value::Double(_) => out.push_str("null"),
+            Value::String(v) => write_json_str(out, v),
+            Value::Bytes(v) => {
+                out.push('"');
+                for b in v {
+                    let _ = write!(out, "{:02x}", b);
+                }
+                out.push('"');
+            }
+            Value::Map(entries) => {
+                out.push('{');
+                for (i, (k, v)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    match k {
+                        Value::String(k) => write_json_str(out, k),
+                        k => write_json_str(out, &k.to_json_like_string()),
+                    }
+                    out.push(':');
+                    v.write_json_like(out);
+                }
+                out.push('}');
+            }
+            Value::List(items) => {
+                out.push('[');
+                for (i, v) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    v.write_json_like(out);
+                }
+                out.push(']');
+            }
+            Value::Struct(fields) => {
+                out.push('{');
+                for (i, (tag, v)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    let _ = write!(out, "\"{}\":", tag);
+                    v.write_json_like(out);
+                }
+                out.push('}');
+            }
+            v => {
+                let _ = write!(out, "{}", v.as_i64().unwrap_or_default());
+            }
+        }
+    }
+
+    /// 忽略整型宽度的比较: 整型（包括`Zero`）按数值比较，`Byte`按有符号的Int1解释；
+    /// 浮点数按位比较，map条目按顺序比较，其余与[`JceKey`]的比较方式相同
+    pub fn semantic_eq(&self, other: &Value) -> bool {
+        if let (Some(a), Some(b)) = (self.as_i64(), other.as_i64()) {
+            return a == b;
+        }
+        match (self, other) {
+            (Value::Map(a), Value::Map(b)) => {
+                a.len() == b.len()
+                    && a.iter()
+                        .zip(b)
+                        .all(|((ak, av), (bk, bv))| ak.semantic_eq(bk) && av.semantic_eq(bv))
+            }
+            (Value::Struct(a), Value::Struct(b)) => {
+                a.len() == b.len()
+                    && a.iter()
+                        .zip(b)
+                        .all(|((at, av), (bt, bv))| at == bt && av.semantic_eq(bv))
+            }
+            (Value::List(a), Value::List(b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.semantic_eq(b))
+            }
+            (a, b) => key_eq(a, b),
+        }
+    }
+
+    /// 将`Value::Map`转为以[`JceKey`]为key的`HashMap`，重复的key保留最后一个
+    pub fn into_key_map(self) -> Option<std::collections::HashMap<JceKey, Value>> {
+        match self {
+            Value::Map(entries) => Some(entries.into_iter().map(|(k, v)| (JceKey(k), v)).collect()),
+            _ => None,
+        }
+    }
+}
+
+fn write_json_str(out: &mut String, s: &str) {
+    use std::fmt::Write;
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// 按`deserialize_any`解码，可用于`from_slice::<Value>`
+///
+/// 长度已知的map为`Map`，长度未知且key均为tag的map（即JCE结构体）为`Struct`
+impl<'de> de::Deserialize<'de> for Value {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> de::Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("any JCE value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> std::result::Result<Value, E> {
+        Ok(Value::from(v))
+    }
+    fn visit_i8<E>(self, v: i8) -> std::result::Result<Value, E> {
+        Ok(Value::from(v))
+    }
+    fn visit_i16<E>(self, v: i16) -> std::result::Result<Value, E> {
+        Ok(Value::from(v))
+    }
+    fn visit_i32<E>(self, v: i32) -> std::result::Result<Value, E> {
+        Ok(Value::from(v))
+    }
+    fn visit_i64<E>(self, v: i64) -> std::result::Result<Value, E> {
+        Ok(Value::from(v))
+    }
+    fn visit_u8<E>(self, v: u8) -> std::result::Result<Value, E> {
+        match v {
+            0 => Ok(Value::Zero),
+            v => Ok(Value::from(v)),
+        }
+    }
+    fn visit_u16<E>(self, v: u16) -> std::result::Result<Value, E> {
+        Ok(Value::from(v))
+    }
+    fn visit_u32<E>(self, v: u32) -> std::result::Result<Value, E> {
+        Ok(Value::from(v))
+    }
+    fn visit_u64<E: de::Error>(self, v: u64) -> std::result::Result<Value, E> {
+        i64::try_from(v)
+            .map(Value::Int64)
+            .map_err(|_| E::custom(format!("Integer {} is out of range for Int64", v)))
+    }
+    fn visit_f32<E>(self, v: f32) -> std::result::Result<Value, E> {
+        Ok(Value::Float(v))
+    }
+    fn visit_f64<E>(self, v: f64) -> std::result::Result<Value, E> {
+        Ok(Value::Double(v))
+    }
+    fn visit_str<E>(self, v: &str) -> std::result::Result<Value, E> {
+        Ok(Value::from(v))
+    }
+    fn visit_string<E>(self, v: String) -> std::result::Result<Value, E> {
+        Ok(Value::String(v))
+    }
+    fn visit_bytes<E>(self, v: &[u8]) -> std::result::Result<Value, E> {
+        Ok(Value::Bytes(v.to_vec()))
+    }
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> std::result::Result<Value, E> {
+        Ok(Value::Bytes(v))
+    }
+    fn visit_unit<E>(self) -> std::result::Result<Value, E> {
+        Ok(Value::Zero)
+    }
+    fn visit_none<E>(self) -> std::result::Result<Value, E> {
+        Ok(Value::Zero)
+    }
+    fn visit_some<D: de::Deserializer<'de>>(self, d: D) -> std::result::Result<Value, D::Error> {
+        de::Deserialize::deserialize(d)
+    }
+    fn visit_newtype_struct<D: de::Deserializer<'de>>(
+        self,
+        d: D,
+    ) -> std::result::Result<Value, D::Error> {
+        de::Deserialize::deserialize(d)
+    }
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<Value, A::Error> {
+        // size_hint来自线上的长度，预分配设上限，避免构造的长度导致分配失败
+        let mut items = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Value::List(items))
+    }
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> std::result::Result<Value, A::Error> {
+        let is_struct = map.size_hint().is_none();
+        let mut entries = Vec::new();
+        while let Some(key) = map.next_key::<Value>()? {
+            entries.push((key, map.next_value::<Value>()?));
+        }
+        let tag = |key: &Value| match key {
+            Value::String(s) => s.parse::<u8>().ok(),
+            _ => None,
+        };
+        if is_struct && entries.iter().all(|(k, _)| tag(k).is_some()) {
+            return Ok(Value::Struct(
+                entries
+                    .into_iter()
+                    .filter_map(|(k, v)| Some((tag(&k)?, v)))
+                    .collect(),
+            ));
+        }
+        Ok(Value::Map(entries))
+    }
+}
+
+/// 整型按自身宽度对应: `i8`为`Byte`，`i16`为`Int16`，`i32`为`Int32`，`i64`为`Int64`；
+/// 无符号整型提升到能容纳全部取值的有符号类型: `u8`为`Int16`，`u16`为`Int32`，`u32`为`Int64`。
+/// `u64`无法无损表示，不提供转换
+macro_rules! value_from_int {
+    ($($ty:ty => $variant:ident as $target:ty),* $(,)?) => {
+        $(
+            impl From<$ty> for Value {
+                fn from(v: $ty) -> Self {
+                    Value::$variant(v as $target)
+                }
+            }
+        )*
+    };
+}
+
+value_from_int! {
+    i8 => Byte as u8,
+    i16 => Int16 as i16,
+    i32 => Int32 as i32,
+    i64 => Int64 as i64,
+    u8 => Int16 as i16,
+    u16 => Int32 as i32,
+    u32 => Int64 as i64,
+}
+
+/// `true`为`Byte(1)`，`false`为`Byte(0)`
+impl From<bool> for Value {
+    fn from(v: bool) -> Self {
+        Value::Byte(v as u8)
+    }
+}
+
+impl From<f32> for Value {
+    fn from(v: f32) -> Self {
+        Value::Float(v)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(v: f64) -> Self {
+        Value::Double(v)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(v: &str) -> Self {
+        Value::String(v.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(v: String) -> Self {
+        Value::String(v)
+    }
+}
+
+impl From<Vec<u8>> for Value {
+    fn from(v: Vec<u8>) -> Self {
+        Value::Bytes(v)
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(v: Vec<Value>) -> Self {
+        Value::List(v)
+    }
+}
+
+/// 可作为`HashMap`/`HashSet` key的`Value`
+///
+/// 浮点数按位比较(`to_bits`)，因此`NaN`等于自身而`0.0`与`-0.0`不相等；
+/// 不同宽度的整型视为不同的key
+#[derive(Debug, Clone)]
+pub struct JceKey(pub Value);
+
+impl PartialEq for JceKey {
+    fn eq(&self, other: &Self) -> bool {
+        key_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for JceKey {}
+
+impl std::hash::Hash for JceKey {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        key_hash(&self.0, state)
+    }
+}
+
+/// 与[`Value`]相同，可用于`HashMap<JceKey, Value>`等以任意JCE值为key的map
+impl<'de> de::Deserialize<'de> for JceKey {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<JceKey, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        Value::deserialize(deserializer).map(JceKey)
+    }
+}
+
+fn key_eq(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Byte(a), Value::Byte(b)) => a == b,
+        (Value::Int16(a), Value::Int16(b)) => a == b,
+        (Value::Int32(a), Value::Int32(b)) => a == b,
+        (Value::Int64(a), Value::Int64(b)) => a == b,
+        (Value::Float(a), Value::Float(b)) => a.to_bits() == b.to_bits(),
+        (Value::Double(a), Value::Double(b)) => a.to_bits() == b.to_bits(),
+        (Value::String(a), Value::String(b)) => a == b,
+        (Value::Bytes(a), Value::Bytes(b)) => a == b,
+        (Value::Map(a), Value::Map(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .zip(b)
+                    .all(|((ak, av), (bk, bv))| key_eq(ak, bk) && key_eq(av, bv))
+        }
+        (Value::Struct(a), Value::Struct(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .zip(b)
+                    .all(|((at, av), (bt, bv))| at == bt && key_eq(av, bv))
+        }
+        (Value::List(a), Value::List(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| key_eq(a, b))
+        }
+        (Value::Zero, Value::Zero) => true,
+        _ => false,
+    }
+}
+
+fn key_hash<H: std::hash::Hasher>(value: &Value, state: &mut H) {
+    use std::hash::Hash;
+
+    std::mem::discriminant(value).hash(state);
+    match value {
+        Value::Byte(v) => v.hash(state),
+        Value::Int16(v) => v.hash(state),
+        Value::Int32(v) => v.hash(state),
+        Value::Int64(v) => v.hash(state),
+        Value::Float(v) => v.to_bits().hash(state),
+        Value::Double(v) => v.to_bits().hash(state),
+        Value::String(v) => v.hash(state),
+        Value::Bytes(v) => v.hash(state),
+        Value::Map(entries) => {
+            entries.len().hash(state);
+            for (k, v) in entries {
+                key_hash(k, state);
+                key_hash(v, state);
+            }
+        }
+        Value::Struct(fields) => {
+            fields.len().hash(state);
+            for (tag, v) in fields {
+                tag.hash(state);
+                key_hash(v, state);
+            }
+        }
+        Value::List(items) => {
+            items.len().hash(state);
+            for v in items {
+                key_hash(v, state);
+            }
+        }
+        Value::Zero => {}
+    }
+}
+
+/// 按线上顺序保留全部条目的map，重复的key不会像`HashMap`那样被合并
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JceMapVec<K, V>(pub Vec<(K, V)>);
+
+impl<K: serde::Serialize, V: serde::Serialize> serde::Serialize for JceMapVec<K, V> {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|(k, v)| (k, v)))
+    }
+}
+
+impl<'de, K: de::Deserialize<'de>, V: de::Deserialize<'de>> de::Deserialize<'de>
+    for JceMapVec<K, V>
+{
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct MapVecVisitor<K, V>(std::marker::PhantomData<(K, V)>);
+
+        impl<'de, K: de::Deserialize<'de>, V: de::Deserialize<'de>> de::Visitor<'de>
+            for MapVecVisitor<K, V>
+        {
+            type Value = JceMapVec<K, V>;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a JCE map")
+            }
+
+            fn visit_map<A: de::MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> std::result::Result<Self::Value, A::Error> {
+                let mut entries = Vec::new();
+                while let Some(entry) = map.next_entry()? {
+                    entries.push(entry);
+                }
+                Ok(JceMapVec(entries))
+            }
+        }
+
+        deserializer.deserialize_map(MapVecVisitor(std::marker::PhantomData))
+    }
+}
+
+pub(crate) const SIMPLE_LIST_TOKEN: &str = "$serde_jce::SimpleList";
+
+/// 可指定元素类型的SimpleList
+///
+/// 标准的SimpleList（即`serialize_bytes`写出的字节数组）元素类型为0（Int1），
+/// 部分schema用其他元素类型承载字节数据，此时用该类型原样读写元素类型
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SimpleList {
+    pub element_type: u8,
+    pub data: Vec<u8>,
+}
+
+impl serde::Serialize for SimpleList {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        // 元素类型放在首字节，由JCE序列化器拆出
+        let mut buf = Vec::with_capacity(self.data.len() + 1);
+        buf.push(self.element_type);
+        buf.extend_from_slice(&self.data);
+        serializer.serialize_newtype_struct(SIMPLE_LIST_TOKEN, serde_bytes::Bytes::new(&buf))
+    }
+}
+
+impl<'de> de::Deserialize<'de> for SimpleList {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct SimpleListVisitor;
+
+        impl<'de> de::Visitor<'de> for SimpleListVisitor {
+            type Value = SimpleList;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a SimpleList")
+            }
+
+            fn visit_newtype_struct<D: de::Deserializer<'de>>(
+                self,
+                d: D,
+            ) -> std::result::Result<SimpleList, D::Error> {
+                let buf = <serde_bytes::ByteBuf as de::Deserialize>::deserialize(d)?.into_vec();
+                let (element_type, data) = buf
+                    .split_first()
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                Ok(SimpleList {
+                    element_type: *element_type,
+                    data: data.to_vec(),
+                })
+            }
+        }
+
+        deserializer.deserialize_newtype_struct(SIMPLE_LIST_TOKEN, SimpleListVisitor)
+    }
+}
+
+/// JCE类型的可读名称
+pub fn type_name(typ: u8) -> &'static str {
+    match typ {
+        0 => "Int1",
+        1 => "Int2",
+        2 => "Int4",
+        3 => "Int8",
+        4 => "Float",
+        5 => "Double",
+        6 => "String1",
+        7 => "String4",
+        8 => "Map",
+        9 => "List",
+        10 => "StructBegin",
+        11 => "StructEnd",
+        12 => "Zero",
+        13 => "SimpleList",
+        _ => "Unknown",
+    }
+}
+
+/// 跳过未知字段时允许的最大嵌套层数
+const MAX_SKIP_DEPTH: usize = 100;
+
+/// JCE反序列化器
+///
+/// 对应Tars IDL的字段修饰: `require`字段直接声明为普通字段，缺失时报`missing field`错误；
+/// `optional`字段声明为`Option<T>`或加`#[serde(default)]`，缺失时为`None`或默认值
+///
+/// 字段重新编号时可用`#[serde(rename = "5", alias = "2")]`同时接受新旧tag，序列化只写新tag
 pub struct Deserializer<R> {
     reader: R,
     peeked_header: Option<(u8, u8)>,
     current_type: Option<u8>,
+    /// 当前值按位置解析，Zero表示`None`: 开启`with_positional_none`时的列表元素与map的key/value，
+    /// 以及枚举的变体内容
+    positional: bool,
+    /// 当前值是结构体字段，而不是列表元素、map的key/value或顶层值
+    struct_field: bool,
+    /// 正在读[`SimpleList`]，接受任意元素类型并把它放在字节数组的首字节
+    raw_simple_list: bool,
+    /// 构造时的输入长度，用于在错误中给出偏移量
+    start_len: Option<usize>,
+    /// 读最近一个头部之前的剩余输入长度，用于计算该头部的偏移量
+    header_remaining: Option<usize>,
+    /// 按`UnknownTypePolicy::Stop`在未知类型处停止后，之后的输入视为已结束
+    stopped: bool,
+    config: Config,
+}
+
+/// 线上类型与目标类型不完全匹配时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Coercion {
+    /// 会丢失信息的转换视为schema不匹配，返回错误
+    #[default]
+    Strict,
+    /// 按`as`语义转换，SimpleList不检查元素类型
+    Lenient,
+}
+
+/// 跳过字段时遇到未知类型的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnknownTypePolicy {
+    /// 返回错误，错误信息中带有该头部的偏移量（输入长度已知时）
+    #[default]
+    Error,
+    /// 停在该头部处，之后的输入视为已结束，用于容忍协议扩展的转发场景
+    Stop,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Config {
+    coercion: Coercion,
+    max_known_tag: Option<u8>,
+    max_alloc: Option<usize>,
+    max_collection_len: Option<usize>,
+    unbounded_lists: bool,
+    unknown_type: UnknownTypePolicy,
+    trailing_data: bool,
+    allow_missing_terminator: bool,
+    positional_none: bool,
 }
 
-struct TagIdentifier(pub u8);
+/// 结构体字段的tag，声明了对应字段名时（如十六进制写法的`"0x0A"`）按该名称匹配
+struct TagIdentifier(pub u8, Option<&'static str>);
 
 struct StructAccessor<'a, R> {
     de: &'a mut Deserializer<R>,
+    /// 顶层结构体体: 允许末尾带一个结束符，结束符之后不能再有数据
+    root: bool,
+    /// 目标结构体声明的字段名，用于把tag对应回字段名
+    fields: &'static [&'static str],
+    /// 上一个匹配字段的下一位，字段按tag顺序声明时下一个tag通常就在这里
+    next_field: usize,
 }
 
 impl<'a, R> StructAccessor<'a, R> {
     fn new(de: &'a mut Deserializer<R>) -> Self {
-        Self { de }
+        Self {
+            de,
+            root: false,
+            fields: &[],
+            next_field: 0,
+        }
+    }
+
+    fn root(de: &'a mut Deserializer<R>) -> Self {
+        Self {
+            de,
+            root: true,
+            fields: &[],
+            next_field: 0,
+        }
+    }
+
+    fn with_fields(mut self, fields: &'static [&'static str]) -> Self {
+        self.fields = fields;
+        self
+    }
+}
+
+struct EnumAccessor<'a, R> {
+    de: &'a mut Deserializer<R>,
+    /// 顶层的枚举没有头部和结束符
+    framed: bool,
+}
+
+impl<'a, R> EnumAccessor<'a, R> {
+    fn new(de: &'a mut Deserializer<R>, framed: bool) -> Self {
+        Self { de, framed }
     }
 }
 
@@ -41,6 +783,8 @@ struct SeqAccessor<'a, R> {
     de: &'a mut Deserializer<R>,
     len: usize,
     current: usize,
+    /// 长度未写明，读到结构体结束符、非0的tag或输入结束为止
+    until_end: bool,
 }
 
 impl<'a, R> SeqAccessor<'a, R> {
@@ -49,6 +793,16 @@ impl<'a, R> SeqAccessor<'a, R> {
             de,
             len,
             current: 0,
+            until_end: false,
+        }
+    }
+
+    fn until_end(de: &'a mut Deserializer<R>) -> Self {
+        Self {
+            de,
+            len: usize::MAX,
+            current: 0,
+            until_end: true,
         }
     }
 }
@@ -69,9 +823,13 @@ impl<'a, R> MapAccessor<'a, R> {
     }
 }
 
-impl<'de, R: Read> de::Deserializer<'de> for &mut Deserializer<R> {
+impl<'de, R: JceInput<'de>> de::Deserializer<'de> for &mut Deserializer<R> {
     type Error = Error;
 
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
     fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
@@ -133,6 +891,8 @@ impl<'de, R: Read> de::Deserializer<'de> for &mut Deserializer<R> {
     {
         visitor.visit_u32(self.get_number()? as u32)
     }
+    /// JCE没有无符号类型，大于`i64::MAX`的u64按位重新解释为负的i64后按最短形式写入（如`u64::MAX`为Int1的`0xFF`），
+    /// 这里把读到的整数按位重新解释回u64，整个取值范围都能原样往返
     fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
@@ -149,56 +909,87 @@ impl<'de, R: Read> de::Deserializer<'de> for &mut Deserializer<R> {
     where
         V: de::Visitor<'de>,
     {
-        let typ = self
-            .current_type
-            .take()
-            .ok_or(Error::Message("Missing type".into()))?;
+        let typ = self.take_type()?;
         visitor.visit_f32(match typ {
             4 => self.read_f32()?,
-            5 => self.read_f64()? as f32,
-            _ => return Err(Error::Message(format!("Invalid int type {}", typ))),
+            5 if self.config.coercion == Coercion::Lenient => self.read_f64()? as f32,
+            5 => {
+                return Err(Error::Message(
+                    "Double cannot be narrowed to f32 in strict mode".into(),
+                ));
+            }
+            // 对端可能将整数值的浮点数按整型编码
+            0..=3 | 12 => self.read_number(typ)? as f32,
+            _ => {
+                return Err(Error::UnexpectedType {
+                    expected: 4,
+                    found: typ,
+                });
+            }
         })
     }
     fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        let typ = self
-            .current_type
-            .take()
-            .ok_or(Error::Message("Missing type".into()))?;
+        let typ = self.take_type()?;
         visitor.visit_f64(match typ {
             4 => self.read_f32()? as f64,
             5 => self.read_f64()?,
-            _ => return Err(Error::Message(format!("Invalid int type {}", typ))),
+            0..=3 | 12 => self.read_number(typ)? as f64,
+            _ => {
+                return Err(Error::UnexpectedType {
+                    expected: 5,
+                    found: typ,
+                });
+            }
         })
     }
     fn deserialize_char<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_u8(visitor)
+        // char按Unicode标量值的整型编码，也接受只含一个字符的字符串
+        match self.current_type {
+            Some(typ @ (6 | 7)) => {
+                self.current_type = None;
+                let s = raw::read_string(&mut self.reader, typ)?;
+                let mut chars = s.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => visitor.visit_char(c),
+                    _ => Err(Error::Message(format!(
+                        "Expected a single char, got {:?}",
+                        s
+                    ))),
+                }
+            }
+            _ => {
+                let v = self.get_number()?;
+                let c = u32::try_from(v)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| Error::Message(format!("Invalid char scalar {}", v)))?;
+                visitor.visit_char(c)
+            }
+        }
     }
     fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        let typ = self
-            .current_type
-            .take()
-            .ok_or(Error::Message("No type".into()))?;
-        let len = match typ {
-            6 => self.read_u8()? as usize,
-            7 => self.read_u32()? as usize,
-            _ => return Err(Error::Message("Not a string type".into())),
-        };
-
-        let mut buf = vec![0u8; len];
-        self.reader.read_exact(&mut buf)?;
-
-        let s = std::str::from_utf8(&buf).map_err(|_| Error::Message("Invalid UTF-8".into()))?;
-
-        visitor.visit_str(s)
+        let typ = self.take_type()?;
+        if typ != 6 && typ != 7 {
+            return Err(Error::UnexpectedType {
+                expected: 6,
+                found: typ,
+            });
+        }
+        let len = self.read_string_len(typ)?;
+        // 切片输入可借用，`&str`字段（包括`Box<[&str]>`的元素）不需要拷贝
+        match self.read_str(len)? {
+            Cow::Borrowed(s) => visitor.visit_borrowed_str(s),
+            Cow::Owned(s) => visitor.visit_string(s),
+        }
     }
     fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
     where
@@ -210,25 +1001,30 @@ impl<'de, R: Read> de::Deserializer<'de> for &mut Deserializer<R> {
     where
         V: de::Visitor<'de>,
     {
-        let typ = self
-            .current_type
-            .take()
-            .ok_or(Error::Message("Missing type".into()))?;
+        let typ = self.take_type()?;
         if typ != 13 {
-            return Err(Error::Message("Expected SimpleList".into()));
+            return Err(Error::UnexpectedType {
+                expected: 13,
+                found: typ,
+            });
         }
 
-        let (_, element_typ) = self.next_header()?;
-        if element_typ != 0 {
-            return Err(Error::Message(
-                "SimpleList must be followed by Type 0".into(),
-            ));
+        if std::mem::take(&mut self.raw_simple_list) {
+            let (_, element_type) = self.next_header()?;
+            let len = self.get_length()?;
+            self.check_len(len as u64)?;
+            let mut buf = Vec::with_capacity(len + 1);
+            buf.push(element_type);
+            buf.extend_from_slice(&self.reader.read_bytes(len)?);
+            return visitor.visit_byte_buf(buf);
+        }
+        self.read_simple_list_element()?;
+        let len = self.get_length()?;
+        self.check_len(len as u64)?;
+        match self.reader.read_bytes(len)? {
+            Cow::Borrowed(buf) => visitor.visit_borrowed_bytes(buf),
+            Cow::Owned(buf) => visitor.visit_byte_buf(buf),
         }
-        let len = self.get_raw_number()? as usize;
-        let mut buf = vec![0u8; len];
-        self.reader.read_exact(&mut buf)?;
-
-        visitor.visit_byte_buf(buf)
     }
     fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
     where
@@ -236,48 +1032,98 @@ impl<'de, R: Read> de::Deserializer<'de> for &mut Deserializer<R> {
     {
         self.deserialize_bytes(visitor)
     }
+    /// 按位置解析的值（见`with_positional_none`）中Zero表示`None`；结构体字段缺失即为`None`，出现的Zero为`Some(0)`
     fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
+        if self.positional && self.current_type == Some(12) {
+            self.current_type = None;
+            return visitor.visit_none();
+        }
         visitor.visit_some(self)
     }
-    fn deserialize_unit<V>(self, _visitor: V) -> Result<V::Value>
+    /// `()`与单元结构体相同，对应空结构体
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        todo!()
+        de::Deserializer::deserialize_unit_struct(self, "", visitor)
     }
-    fn deserialize_unit_struct<V>(self, _name: &'static str, _visitor: V) -> Result<V::Value>
+    /// 单元结构体对应空结构体，其中的字段被忽略
+    fn deserialize_unit_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        todo!()
+        de::Deserializer::deserialize_struct(&mut *self, name, &[], de::IgnoredAny)?;
+        visitor.visit_unit()
     }
-    fn deserialize_newtype_struct<V>(self, _name: &'static str, _visitor: V) -> Result<V::Value>
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        todo!()
+        if name == SIMPLE_LIST_TOKEN {
+            self.raw_simple_list = true;
+            let result = visitor.visit_newtype_struct(&mut *self);
+            self.raw_simple_list = false;
+            return result;
+        }
+        visitor.visit_newtype_struct(self)
     }
     fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        let typ = self.current_type.take();
+        // 顶层的list没有预先读到的头部
+        let typ = self.take_type()?;
 
-        if typ != Some(9) {
-            return Err(Error::Message("Missign Type".into()));
+        if typ != 9 {
+            return Err(Error::UnexpectedType {
+                expected: 9,
+                found: typ,
+            });
+        }
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!("jce_list", offset = ?self.header_offset()).entered();
+        let (tag, len_typ) = self.next_header()?;
+        if len_typ == 12 && self.config.unbounded_lists && self.struct_field {
+            // 长度为Zero且其后紧跟tag 0的元素时才读到结构体结束，否则是真正的空列表
+            match self.next_header_or_eof()? {
+                Some((0, typ)) if typ != 11 => {
+                    self.peek_header(0, typ);
+                    return visitor.visit_seq(SeqAccessor::until_end(self));
+                }
+                Some((next_tag, typ)) => self.peek_header(next_tag, typ),
+                None => {}
+            }
+            return visitor.visit_seq(SeqAccessor::new(self, 0));
         }
-        let len = self.get_raw_number()? as usize;
+        self.peek_header(tag, len_typ);
+        let len = self.get_collection_len()?;
         let value = visitor.visit_seq(SeqAccessor::new(self, len))?;
         Ok(value)
     }
-    fn deserialize_tuple<V>(self, _: usize, visitor: V) -> Result<V::Value>
+    /// 元组按list编码，线上的元素个数必须与声明的长度一致
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_seq(visitor)
+        let typ = self.take_type()?;
+
+        if typ != 9 {
+            return Err(Error::UnexpectedType {
+                expected: 9,
+                found: typ,
+            });
+        }
+        let wire_len = self.get_collection_len()?;
+        if wire_len != len {
+            return Err(de::Error::invalid_length(
+                wire_len,
+                &format!("a list of {} elements", len).as_str(),
+            ));
+        }
+        visitor.visit_seq(SeqAccessor::new(self, len))
     }
     fn deserialize_tuple_struct<V>(
         self,
@@ -290,39 +1136,55 @@ impl<'de, R: Read> de::Deserializer<'de> for &mut Deserializer<R> {
     {
         self.deserialize_tuple(len, visitor)
     }
+    /// Map的每个条目为tag 0的key与tag 1的value，value可以是任意类型（包括结构体），
+    /// 如`HashMap<String, Inner>`直接声明即可
     fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        let typ = self
-            .current_type
-            .take()
-            .ok_or(Error::Message("Missing type".into()))?;
-        if typ != 8 {
-            return Err(Error::Message(format!("Expected Map(8), got {}", typ)));
+        // 顶层总是按结构体体解析为 tag -> value；单独编码的map是tag 0的字段，用`from_slice_field`读取
+        let Some(typ) = self.current_type.take() else {
+            return visitor.visit_map(StructAccessor::root(self));
+        };
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!("jce_map", typ, offset = ?self.header_offset()).entered();
+        match typ {
+            8 => {
+                let len = self.get_collection_len()?;
+                visitor.visit_map(MapAccessor::new(self, len))
+            }
+            // 结构体按 tag -> value 解析为map
+            10 => visitor.visit_map(StructAccessor::new(self)),
+            _ => Err(Error::UnexpectedType {
+                expected: 8,
+                found: typ,
+            }),
         }
-
-        let len = self.get_raw_number()? as usize;
-
-        visitor.visit_map(MapAccessor::new(self, len))
     }
     fn deserialize_struct<V>(
         self,
         _name: &'static str,
-        _fields: &'static [&'static str],
+        fields: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
+        #[cfg(feature = "tracing")]
+        let _span =
+            tracing::trace_span!("jce_struct", name = _name, offset = ?self.header_offset())
+                .entered();
         let typ = self.current_type.take();
         match typ {
             Some(10) => {
-                let value = visitor.visit_map(StructAccessor::new(self))?;
+                let value = visitor.visit_map(StructAccessor::new(self).with_fields(fields))?;
                 Ok(value)
             }
-            None => visitor.visit_map(StructAccessor::new(self)),
-            Some(t) => Err(Error::Message(format!("Expected struct (10), found {}", t))),
+            None => visitor.visit_map(StructAccessor::root(self).with_fields(fields)),
+            Some(t) => Err(Error::UnexpectedType {
+                expected: 10,
+                found: t,
+            }),
         }
     }
     fn deserialize_enum<V>(
@@ -334,47 +1196,221 @@ impl<'de, R: Read> de::Deserializer<'de> for &mut Deserializer<R> {
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_seq(visitor)
+        // 枚举为结构体: tag 0为变体序号，tag 1为变体内容；紧凑枚举只有变体序号
+        if let Some(0..=3 | 12) = self.current_type {
+            let index = self.get_number()?;
+            let index = u32::try_from(index)
+                .map_err(|_| Error::Message(format!("Invalid enum variant index {}", index)))?;
+            return visitor.visit_enum(de::IntoDeserializer::<Error>::into_deserializer(index));
+        }
+        match self.current_type.take() {
+            Some(10) => visitor.visit_enum(EnumAccessor::new(self, true)),
+            None => visitor.visit_enum(EnumAccessor::new(self, false)),
+            Some(t) => Err(Error::UnexpectedType {
+                expected: 10,
+                found: t,
+            }),
+        }
     }
-    fn deserialize_identifier<V>(self, _visitor: V) -> Result<V::Value>
+    /// 字符串按名称匹配，整型按序号匹配（如内部标签枚举的判别字段）
+    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        todo!()
+        match self.current_type {
+            Some(0..=3 | 12) => {
+                let v = self.get_number()?;
+                let index = u64::try_from(v)
+                    .map_err(|_| Error::Message(format!("Invalid identifier index {}", v)))?;
+                visitor.visit_u64(index)
+            }
+            _ => self.deserialize_str(visitor),
+        }
     }
+    /// 跳过当前值；顶层的值没有已读的头部，此时跳过整个顶层结构体体
     fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        let typ = self.current_type.unwrap();
-        self.skip_type(typ)?;
+        match self.current_type.take() {
+            Some(typ) => self.skip_type(typ)?,
+            None => self.validate()?,
+        }
         visitor.visit_unit()
     }
-    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value>
+    /// 按线上类型分派: 整型按自身宽度调用`visit_i8`..`visit_i64`，结构体为以tag字符串为key的map，
+    /// `Zero`按整数0调用`visit_u8(0)`
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        let Some(typ) = self.current_type else {
+            return self.deserialize_any_root(visitor);
+        };
+        match typ {
+            0 => visitor.visit_i8(self.get_number()? as i8),
+            1 => visitor.visit_i16(self.get_number()? as i16),
+            2 => visitor.visit_i32(self.get_number()? as i32),
+            3 => visitor.visit_i64(self.get_number()?),
+            4 => self.deserialize_f32(visitor),
+            5 => self.deserialize_f64(visitor),
+            6 | 7 => self.deserialize_string(visitor),
+            8 | 10 => self.deserialize_map(visitor),
+            9 => self.deserialize_seq(visitor),
+            // Zero可以表示任何类型的零值，按整数0访问；其余类型的整型不会调用visit_u8，
+            // 因此`Value`可以据此还原出`Zero`
+            12 => {
+                self.current_type = None;
+                visitor.visit_u8(0)
+            }
+            13 => self.deserialize_bytes(visitor),
+            _ => Err(Error::Message(format!("Unexpected type {}", typ))),
+        }
+    }
+}
+
+impl<'de, 'a, R: JceInput<'de>> de::EnumAccess<'de> for EnumAccessor<'a, R> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self)>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let (tag, typ) = self.de.next_header()?;
+        if tag != 0 {
+            return Err(Error::Message(format!(
+                "Expected enum variant index at tag 0, found tag {}",
+                tag
+            )));
+        }
+        self.de.current_type = Some(typ);
+        let index = self.de.get_number()?;
+        let index = u32::try_from(index)
+            .map_err(|_| Error::Message(format!("Invalid enum variant index {}", index)))?;
+        let value = seed.deserialize(de::IntoDeserializer::<Error>::into_deserializer(index))?;
+        Ok((value, self))
+    }
+}
+
+impl<'de, 'a, R: JceInput<'de>> de::VariantAccess<'de> for EnumAccessor<'a, R> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        self.end()
+    }
+
+    fn newtype_variant_seed<T>(mut self, seed: T) -> Result<T::Value>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        self.content()?;
+        let value = seed.deserialize(&mut *self.de)?;
+        self.end()?;
+        Ok(value)
+    }
+
+    fn tuple_variant<V>(mut self, len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.content()?;
+        let value = de::Deserializer::deserialize_tuple(&mut *self.de, len, visitor)?;
+        self.end()?;
+        Ok(value)
+    }
+
+    fn struct_variant<V>(mut self, fields: &'static [&'static str], visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        todo!()
+        self.content()?;
+        let value = de::Deserializer::deserialize_struct(&mut *self.de, "", fields, visitor)?;
+        self.end()?;
+        Ok(value)
+    }
+}
+
+impl<'de, 'a, R: JceInput<'de>> EnumAccessor<'a, R> {
+    /// 读取tag 1的变体内容头部
+    fn content(&mut self) -> Result<()> {
+        let (tag, typ) = self.de.next_header()?;
+        if tag != 1 {
+            return Err(Error::Message(format!(
+                "Expected enum content at tag 1, found tag {}",
+                tag
+            )));
+        }
+        self.de.current_type = Some(typ);
+        self.de.positional = true;
+        self.de.struct_field = false;
+        Ok(())
+    }
+
+    /// 有头部的枚举结构体需要读到结束符
+    fn end(self) -> Result<()> {
+        if self.framed {
+            let (_, typ) = self.de.next_header()?;
+            if typ != 11 {
+                return Err(Error::Message(format!(
+                    "Expected end of enum struct, found type {}",
+                    typ
+                )));
+            }
+        }
+        Ok(())
     }
 }
 
-impl<'de, 'a, R: Read> serde::de::MapAccess<'de> for StructAccessor<'a, R> {
+impl<'de, 'a, R: JceInput<'de>> serde::de::MapAccess<'de> for StructAccessor<'a, R> {
     type Error = Error;
     fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
     where
         K: DeserializeSeed<'de>,
     {
-        let (tag, typ) = match self.de.next_header() {
-            Ok(h) => h,
-            Err(_) => return Ok(None),
+        let (tag, typ) = loop {
+            let (tag, typ) = match self.de.next_header_or_eof()? {
+                Some(h) => h,
+                // 顶层结构体体的结束符是可选的
+                None if self.root => return Ok(None),
+                None => return self.de.missing_terminator().map(|_| None),
+            };
+            if typ == 11 {
+                if self.root {
+                    self.de.end_of_root()?;
+                }
+                return Ok(None);
+            }
+            match self.de.config.max_known_tag {
+                Some(max) if tag > max => match self.de.config.coercion {
+                    Coercion::Strict => {
+                        return Err(Error::Message(format!(
+                            "Unknown tag {} above max known tag {}",
+                            tag, max
+                        )));
+                    }
+                    Coercion::Lenient => self.de.skip_type(typ)?,
+                },
+                _ => break (tag, typ),
+            }
         };
-        if typ == 11 {
-            return Ok(None);
-        }
 
         self.de.current_type = Some(typ);
+        self.de.positional = false;
+        self.de.struct_field = true;
+        #[cfg(feature = "tracing")]
+        tracing::trace!(tag, typ, offset = ?self.de.header_offset(), "jce field");
 
-        seed.deserialize(TagIdentifier(tag)).map(Some)
+        let matches = |name: &&str| raw::parse_tag(name) == Some(tag);
+        let index = match self.fields.get(self.next_field) {
+            Some(name) if matches(name) => Some(self.next_field),
+            _ => self.fields.iter().position(matches),
+        };
+        if let Some(i) = index {
+            self.next_field = i + 1;
+        }
+        let name = index.map(|i| self.fields[i]);
+        seed.deserialize(TagIdentifier(tag, name)).map(Some)
     }
     fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
     where
@@ -384,15 +1420,95 @@ impl<'de, 'a, R: Read> serde::de::MapAccess<'de> for StructAccessor<'a, R> {
     }
 }
 
-impl<R: Read> Deserializer<R> {
+impl<'a> Deserializer<SliceReader<'a>> {
+    /// 从切片反序列化，字节数组可借用输入数据
+    pub fn from_slice(slice: &'a [u8]) -> Self {
+        Deserializer::new(SliceReader::new(slice))
+    }
+}
+
+impl<'de, R: JceInput<'de>> Deserializer<R> {
     pub fn new(reader: R) -> Self {
         Deserializer {
+            start_len: reader.remaining(),
             reader,
             peeked_header: None,
             current_type: None,
+            positional: false,
+            struct_field: false,
+            raw_simple_list: false,
+            header_remaining: None,
+            stopped: false,
+            config: Config::default(),
         }
     }
 
+    pub fn with_coercion(mut self, coercion: Coercion) -> Self {
+        self.config.coercion = coercion;
+        self
+    }
+
+    /// 结构体中出现大于该值的tag时，Strict模式报错，Lenient模式跳过该字段
+    pub fn with_max_known_tag(mut self, tag: u8) -> Self {
+        self.config.max_known_tag = Some(tag);
+        self
+    }
+
+    /// 非标准扩展: 结构体字段中的列表长度写为`Zero`但其后紧跟tag 0的元素时，一直读到所在结构体的结束符
+    /// （或tag不为0的头部、输入结束）为止，长度为Zero且没有元素时仍是空列表。
+    /// 部分编码器不写准确的列表长度时可开启
+    pub fn with_unbounded_lists(mut self, enabled: bool) -> Self {
+        self.config.unbounded_lists = enabled;
+        self
+    }
+
+    /// 列表元素与map的key/value中，Zero解码为`None`，与`Serializer::with_positional_none`配合使用；
+    /// 默认关闭，Zero总是解码为`Some(0)`
+    pub fn with_positional_none(mut self, enabled: bool) -> Self {
+        self.config.positional_none = enabled;
+        self
+    }
+
+    /// 单个字符串/字节数组的最大长度，跳过未知字段时同样生效
+    pub fn with_max_alloc(mut self, bytes: usize) -> Self {
+        self.config.max_alloc = Some(bytes);
+        self
+    }
+
+    /// 单个list/map的最大元素个数，与`max_alloc`分别限制
+    pub fn with_max_collection_len(mut self, len: usize) -> Self {
+        self.config.max_collection_len = Some(len);
+        self
+    }
+
+    /// 输入在嵌套结构体的结束符之前结束时，视为该结构体已结束而不报错，用于读取被截断的抓包数据
+    pub fn with_allow_missing_terminator(mut self, enabled: bool) -> Self {
+        self.config.allow_missing_terminator = enabled;
+        self
+    }
+
+    /// 顶层结构体以结束符结尾时，其后允许还有数据（如拼接在一起的多个结构体）
+    pub fn with_trailing_data(mut self, enabled: bool) -> Self {
+        self.config.trailing_data = enabled;
+        self
+    }
+
+    pub fn with_unknown_type_policy(mut self, policy: UnknownTypePolicy) -> Self {
+        self.config.unknown_type = policy;
+        self
+    }
+
+    /// 是否已按`UnknownTypePolicy::Stop`在未知类型处停止
+    pub fn stopped_at_unknown_type(&self) -> bool {
+        self.stopped
+    }
+
+    /// 读下一个头部并解码对应的值，返回(tag, 值)；输入结束或遇到结构体结束符时返回错误
+    pub fn read_next_value(&mut self) -> Result<(u8, Value)> {
+        let (tag, typ) = self.next_header()?;
+        Ok((tag, self.deserialize_any_value(typ)?))
+    }
+
     pub fn deserialize_any_value(&mut self, typ: u8) -> Result<Value> {
         self.current_type = Some(typ);
 
@@ -400,31 +1516,17 @@ impl<R: Read> Deserializer<R> {
             0 => Ok(Value::Byte(self.read_u8()?)),
             1 => Ok(Value::Int16(self.read_u16()? as i16)),
             2 => Ok(Value::Int32(self.read_u32()? as i32)),
-            3 => Ok(Value::Int64(self.read_u32()? as i64)),
+            3 => Ok(Value::Int64(self.read_u64()? as i64)),
             4 => Ok(Value::Float(self.read_f32()?)),
             5 => Ok(Value::Double(self.read_f64()?)),
-            6 | 7 => Ok(Value::String({
-                let typ = self
-                    .current_type
-                    .take()
-                    .ok_or(Error::Message("No type".into()))?;
-                let len = match typ {
-                    6 => self.read_u8()? as usize,
-                    7 => self.read_u32()? as usize,
-                    _ => return Err(Error::Message("Not a string type".into())),
-                };
-
-                let mut buf = vec![0u8; len];
-                self.reader.read_exact(&mut buf)?;
-
-                let s = std::str::from_utf8(&buf)
-                    .map_err(|_| Error::Message("Invalid UTF-8".into()))?;
-
-                s.into()
-            })),
+            6 | 7 => {
+                self.current_type = None;
+                let len = self.read_string_len(typ)?;
+                Ok(Value::String(self.read_str(len)?.into_owned()))
+            }
             8 => {
-                let len = self.get_raw_number()? as usize;
-                let mut map_vec = Vec::with_capacity(len);
+                let len = self.get_collection_len()?;
+                let mut map_vec = Vec::with_capacity(self.prealloc(len));
                 for _ in 0..len {
                     let (_, k_ty) = self.next_header()?;
                     let key = self.deserialize_any_value(k_ty)?;
@@ -435,8 +1537,8 @@ impl<R: Read> Deserializer<R> {
                 Ok(Value::Map(map_vec))
             }
             9 => {
-                let len = self.get_raw_number()? as usize;
-                let mut list = Vec::with_capacity(len);
+                let len = self.get_collection_len()?;
+                let mut list = Vec::with_capacity(self.prealloc(len));
 
                 for _ in 0..len {
                     let (_, e_ty) = self.next_header()?;
@@ -448,9 +1550,11 @@ impl<R: Read> Deserializer<R> {
             10 => {
                 let mut fields = std::collections::BTreeMap::new();
                 loop {
-                    let (t, ty) = self.next_header()?;
+                    let Some((t, ty)) = self.next_header_or_eof()? else {
+                        self.missing_terminator()?;
+                        break;
+                    };
                     if ty == 11 {
-                        let _ = self.next_header();
                         break;
                     }
                     let val = self.deserialize_any_value(ty)?;
@@ -460,31 +1564,38 @@ impl<R: Read> Deserializer<R> {
             }
             11 => Err(Error::Message("Unexpected Struct End".into())),
             12 => Ok(Value::Zero),
-            13 => Ok(Value::Bytes({
-                let typ = self
-                    .current_type
-                    .take()
-                    .ok_or(Error::Message("Missing type".into()))?;
-                if typ != 13 {
-                    return Err(Error::Message("Expected SimpleList".into()));
-                }
-
-                let (_, element_typ) = self.next_header()?;
-                if element_typ != 0 {
-                    return Err(Error::Message(
-                        "SimpleList must be followed by Type 0".into(),
-                    ));
-                }
-                let len = self.get_raw_number()? as usize;
-                let mut buf = vec![0u8; len];
-                self.reader.read_exact(&mut buf)?;
-                buf
-            })),
+            13 => {
+                self.current_type = None;
+                self.read_simple_list_element()?;
+                let len = self.get_length()?;
+                self.check_len(len as u64)?;
+                Ok(Value::Bytes(self.reader.read_bytes(len)?.into_owned()))
+            }
             _ => Err(Error::Message(format!("Unkown Type: {}", typ))),
         }
     }
 
+    /// 读SimpleList的元素类型头部，Strict下要求为Int1，Lenient下忽略声明的元素类型按字节读取
+    fn read_simple_list_element(&mut self) -> Result<()> {
+        let (_, element_typ) = self.next_header()?;
+        if element_typ != 0 && self.config.coercion == Coercion::Strict {
+            return Err(raw::simple_list_element_error(element_typ));
+        }
+        Ok(())
+    }
+
     fn skip_type(&mut self, typ: u8) -> Result<()> {
+        self.skip_nested(typ, 0)
+    }
+
+    /// depth为当前所在容器的嵌套层数
+    fn skip_nested(&mut self, typ: u8, depth: usize) -> Result<()> {
+        if depth > MAX_SKIP_DEPTH {
+            return Err(Error::Message(format!(
+                "Nesting too deep while skipping (depth {})",
+                depth
+            )));
+        }
         match typ {
             0 => {
                 self.read_u8()?;
@@ -514,41 +1625,156 @@ impl<R: Read> Deserializer<R> {
             }
             8 => {
                 let len = self.get_raw_number()?;
-                for _ in 0..len * 2 {
+                let entries = len
+                    .checked_mul(2)
+                    .ok_or_else(|| Error::Message(format!("Invalid map length {}", len)))?;
+                let len = usize::try_from(len)
+                    .map_err(|_| Error::Message(format!("Invalid length {}", len)))?;
+                self.check_collection_len(len)?;
+                for _ in 0..entries {
                     let (_, t) = self.next_header()?;
-                    self.skip_type(t)?;
+                    self.skip_nested(t, depth + 1)?;
+                    if self.stopped {
+                        break;
+                    }
                 }
             }
             9 => {
-                let len = self.get_raw_number()?;
+                let len = self.get_collection_len()?;
                 for _ in 0..len {
                     let (_, t) = self.next_header()?;
-                    self.skip_type(t)?;
+                    self.skip_nested(t, depth + 1)?;
+                    if self.stopped {
+                        break;
+                    }
                 }
             }
             10 => loop {
-                let (_, t) = self.next_header()?;
+                let Some((_, t)) = self.next_header_or_eof()? else {
+                    if self.config.allow_missing_terminator {
+                        break;
+                    }
+                    return Err(Error::Message(format!(
+                        "Unterminated struct while skipping (depth {})",
+                        depth + 1
+                    )));
+                };
                 if t == 11 {
                     break;
                 }
-                self.skip_type(t)?;
+                self.skip_nested(t, depth + 1)?;
+                if self.stopped {
+                    break;
+                }
             },
             11 | 12 => {}
             13 => {
                 let _ = self.next_header()?;
-                let len = self.get_raw_number()? as u64;
+                let len = self.get_length()? as u64;
                 self.ignore_bytes(len)?;
             }
-            _ => return Err(Error::Message(format!("Unknown type to skip: {}", typ))),
+            _ if self.config.unknown_type == UnknownTypePolicy::Stop => self.stopped = true,
+            _ => {
+                return Err(Error::Message(match self.header_offset() {
+                    Some(offset) => format!("Unknown type to skip: {} at offset {}", typ, offset),
+                    None => format!("Unknown type to skip: {}", typ),
+                }));
+            }
         }
         Ok(())
     }
 
     fn ignore_bytes(&mut self, len: u64) -> Result<()> {
-        std::io::copy(&mut self.reader.by_ref().take(len), &mut std::io::sink())?;
+        self.check_len(len)?;
+        self.reader.skip(len)
+    }
+
+    /// 在读取或跳过len字节之前检查长度，超过`max_alloc`或剩余输入时直接报错
+    fn check_len(&self, len: u64) -> Result<()> {
+        if let Some(max) = self.config.max_alloc
+            && len > max as u64
+        {
+            return Err(Error::Message(format!(
+                "Length {} exceeds max_alloc {}",
+                len, max
+            )));
+        }
+        if let Some(remaining) = self.reader.remaining()
+            && len > remaining as u64
+        {
+            return Err(Error::Message(format!(
+                "Length {} exceeds remaining input {}",
+                len, remaining
+            )));
+        }
         Ok(())
     }
 
+    /// 顶层的值没有类型信息: 首个头部的tag不为0时按结构体体解析；tag为0时，该值之后没有数据
+    /// 则为单个值（如顶层的list或整型），否则为以tag 0开头的结构体体。
+    /// 切片输入通过向前跳过该值判断，不分配也不影响借用；流式输入无法向前查看，总是按结构体体解析
+    fn deserialize_any_root<V>(&mut self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.next_header_or_eof()? {
+            Some((0, typ)) if typ != 11 && self.is_single_root_value(typ) => {
+                self.current_type = Some(typ);
+                de::Deserializer::deserialize_any(self, visitor)
+            }
+            Some((tag, typ)) => {
+                self.peek_header(tag, typ);
+                visitor.visit_map(StructAccessor::root(self))
+            }
+            None => visitor.visit_map(StructAccessor::root(self)),
+        }
+    }
+
+    /// 刚读到头部的typ类型的值是否恰好占满剩余输入；数据有误时返回false，由之后的解码报告错误
+    fn is_single_root_value(&self, typ: u8) -> bool {
+        let Some(rest) = self.reader.remaining_slice() else {
+            return false;
+        };
+        let mut ahead = Deserializer::from_slice(rest);
+        ahead.config = self.config;
+        ahead.skip_type(typ).is_ok() && ahead.reader.remaining() == Some(0)
+    }
+
+    /// 顶层结构体体末尾的结束符是可选的，但其后不能再有数据（除非开启了`with_trailing_data`）
+    fn end_of_root(&mut self) -> Result<()> {
+        if self.config.trailing_data {
+            return Ok(());
+        }
+        match self.next_header_or_eof()? {
+            None => Ok(()),
+            Some(_) => Err(Error::Message(
+                "Unexpected struct end before the end of input".into(),
+            )),
+        }
+    }
+
+    /// 嵌套结构体缺少结束符就遇到了输入结束
+    fn missing_terminator(&self) -> Result<()> {
+        if self.config.allow_missing_terminator {
+            return Ok(());
+        }
+        Err(Error::Message(
+            "Missing struct terminator at end of input".into(),
+        ))
+    }
+
+    /// 读下一个头部，输入已结束时返回`None`
+    fn next_header_or_eof(&mut self) -> Result<Option<(u8, u8)>> {
+        if let Some(header) = self.peeked_header.take() {
+            return Ok(Some(header));
+        }
+        if self.stopped {
+            return Ok(None);
+        }
+        self.header_remaining = self.reader.remaining();
+        raw::read_header_or_eof(&mut self.reader)
+    }
+
     pub fn deserialize_all(&mut self) -> Result<std::collections::BTreeMap<u8, Value>> {
         let mut root = std::collections::BTreeMap::new();
 
@@ -558,6 +1784,7 @@ impl<R: Read> Deserializer<R> {
             match header {
                 Ok((tag, typ)) => {
                     if typ == 11 {
+                        self.end_of_root()?;
                         break;
                     }
                     let val = self.deserialize_any_value(typ)?;
@@ -572,39 +1799,129 @@ impl<R: Read> Deserializer<R> {
         Ok(root)
     }
 
+    /// 在当前结构体中查找指定tag的字段并解码，跳过其余字段
+    pub fn deserialize_field<T>(&mut self, tag: u8) -> Result<Option<T>>
+    where
+        T: de::Deserialize<'de>,
+    {
+        loop {
+            let Some((t, typ)) = self.next_header_or_eof()? else {
+                return Ok(None);
+            };
+            if typ == 11 {
+                return Ok(None);
+            }
+            if t == tag {
+                self.current_type = Some(typ);
+                self.struct_field = true;
+                return T::deserialize(&mut *self).map(Some);
+            }
+            self.skip_type(typ)?;
+        }
+    }
+
+    /// 已读取的字节数，输入长度未知时为`None`
+    pub(crate) fn offset(&self) -> Option<usize> {
+        Some(self.start_len? - self.reader.remaining()?)
+    }
+
+    /// 最近读到的头部在输入中的偏移量
+    fn header_offset(&self) -> Option<usize> {
+        Some(self.start_len? - self.header_remaining?)
+    }
+
+    /// 逐个读取当前结构体的字段，读到结束符（顶层时也可以是输入结束）为止，结束符会被消耗
+    pub fn field_iter(&mut self) -> FieldIter<'_, R> {
+        FieldIter {
+            de: self,
+            done: false,
+        }
+    }
+
+    /// 按线上顺序读取当前结构体的全部字段，与`deserialize_all`不同，重复的tag都会保留
+    pub fn deserialize_struct_raw(&mut self) -> Result<Vec<(u8, Value)>> {
+        self.field_iter().collect()
+    }
+
+    /// 只检查剩余数据是否为完整的JCE结构，逐个跳过字段而不构造任何值
+    pub fn validate(&mut self) -> Result<()> {
+        while let Some((_, typ)) = self.next_header_or_eof()? {
+            if typ == 11 {
+                return self.end_of_root();
+            }
+            self.skip_type(typ)?;
+        }
+        Ok(())
+    }
+
     pub fn next_header(&mut self) -> Result<(u8, u8)> {
         if let Some(header) = self.peeked_header.take() {
             return Ok(header);
         }
+        if self.stopped {
+            return Err(Error::Message("EOF ERROR".to_string()));
+        }
+        self.header_remaining = self.reader.remaining();
 
-        let mut head = [0u8];
-        self.reader.read_exact(&mut head).map_err(|e| {
-            if e.kind() == std::io::ErrorKind::UnexpectedEof {
+        raw::read_header(&mut self.reader).map_err(|e| match e {
+            Error::Io(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
                 Error::Message("EOF ERROR".to_string())
-            } else {
-                Error::Io(e)
             }
-        })?;
+            e => e,
+        })
+    }
 
-        let mut tag = (head[0] & 0xF0) >> 4;
-        let typ = head[0] & 0x0F;
-        if tag == 15 {
-            let mut ext_tag = [0u8; 1];
-            self.reader.read_exact(&mut ext_tag)?;
-            tag = ext_tag[0];
+    pub fn peek_header(&mut self, tag: u8, typ: u8) {
+        self.peeked_header = Some((tag, typ));
+    }
+
+    /// 读下一个头部并要求其tag为`tag`，成功时返回类型，随后可直接反序列化该字段的值；
+    /// tag不符时头部会被放回
+    pub fn expect_tag(&mut self, tag: u8) -> Result<u8> {
+        let (actual, typ) = self.next_header()?;
+        if actual != tag {
+            self.peek_header(actual, typ);
+            return Err(Error::Message(format!(
+                "Expected tag {}, found tag {}",
+                tag, actual
+            )));
         }
+        self.current_type = Some(typ);
+        Ok(typ)
+    }
 
-        Ok((tag, typ))
+    /// 跳过`expect_tag`已读头部对应的值；没有已读头部时读下一个头部并跳过其值，
+    /// 用于手写解码时有条件地忽略字段
+    pub fn skip_current_value(&mut self) -> Result<()> {
+        let typ = match self.current_type.take() {
+            Some(typ) => typ,
+            None => self.next_header()?.1,
+        };
+        self.skip_type(typ)
     }
 
-    pub fn peek_header(&mut self, tag: u8, typ: u8) {
-        self.peeked_header = Some((tag, typ));
+    /// 读String1/String4的长度，typ须为6或7
+    fn read_string_len(&mut self, typ: u8) -> Result<usize> {
+        let len = if typ == 6 {
+            self.read_u8()? as usize
+        } else {
+            self.read_u32()? as usize
+        };
+        self.check_len(len as u64)?;
+        Ok(len)
+    }
+
+    /// 读len字节的UTF-8字符串，切片输入时借用
+    fn read_str(&mut self, len: usize) -> Result<Cow<'de, str>> {
+        let invalid = || Error::Message("Invalid UTF-8".into());
+        Ok(match self.reader.read_bytes(len)? {
+            Cow::Borrowed(buf) => Cow::Borrowed(std::str::from_utf8(buf).map_err(|_| invalid())?),
+            Cow::Owned(buf) => Cow::Owned(String::from_utf8(buf).map_err(|_| invalid())?),
+        })
     }
 
     fn read_u8(&mut self) -> Result<u8> {
-        let mut buf = [0u8; 1];
-        self.reader.read_exact(&mut buf)?;
-        Ok(buf[0])
+        self.reader.read_byte()
     }
     fn read_u16(&mut self) -> Result<u16> {
         let mut buf = [0u8; 2];
@@ -640,92 +1957,370 @@ impl<R: Read> Deserializer<R> {
     fn get_raw_number(&mut self) -> Result<i64> {
         let (_tag, typ) = self.next_header()?;
         match typ {
-            12 => Ok(0),
-            0 => Ok(self.read_u8()? as i64),
-            1 => Ok(self.read_u16()? as i64),
-            2 => Ok(self.read_u32()? as i64),
-            3 => Ok(self.read_u64()? as i64),
+            0..=3 | 12 => self.read_number(typ),
             _ => Err(Error::Message(format!("Expected number type, got {}", typ))),
         }
     }
 
-    /// 读整型，不消耗tag
-    fn get_number(&mut self) -> Result<i64> {
-        let typ = self
-            .current_type
-            .take()
-            .ok_or(Error::Message("Missing type".into()))?;
-        Ok(match typ {
-            12 => 0,                      // Zero Type
-            0 => self.read_u8()? as i64,  // int1
-            1 => self.read_u16()? as i64, // int2
-            2 => self.read_u32()? as i64, // int4
-            3 => self.read_u64()? as i64,
-            _ => return Err(Error::Message(format!("Invalid int type {}", typ))),
-        })
+    /// 读list/map的元素个数，并按`max_collection_len`检查
+    fn get_collection_len(&mut self) -> Result<usize> {
+        let len = self.get_length()?;
+        self.check_collection_len(len)
     }
-}
-
-impl<'de> de::Deserializer<'de> for TagIdentifier {
-    type Error = Error;
 
-    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
-    where
-        V: de::Visitor<'de>,
-    {
-        visitor.visit_string(self.0.to_string())
+    /// 按`max_collection_len`与剩余输入检查list/map的元素个数
+    fn check_collection_len(&self, len: usize) -> Result<usize> {
+        if let Some(max) = self.config.max_collection_len
+            && len > max
+        {
+            return Err(Error::Message(format!(
+                "Collection length {} exceeds max_collection_len {}",
+                len, max
+            )));
+        }
+        // 每个元素至少有一个字节的头部
+        if let Some(remaining) = self.reader.remaining()
+            && len > remaining
+        {
+            return Err(Error::Message(format!(
+                "Collection length {} exceeds remaining input {}",
+                len, remaining
+            )));
+        }
+        Ok(len)
     }
 
-    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
-    where
-        V: de::Visitor<'de>,
-    {
-        self.deserialize_any(visitor)
+    /// 按线上长度预分配的元素个数: 不超过剩余输入的字节数，输入长度未知时不预分配
+    fn prealloc(&self, len: usize) -> usize {
+        len.min(self.reader.remaining().unwrap_or(0))
     }
 
-    serde::forward_to_deserialize_any! {
-        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
-        bytes byte_buf option unit unit_struct newtype_struct seq tuple
-        tuple_struct map struct enum ignored_any
+    /// 取出由访问器设置的类型；顶层的值没有访问器，此时读取首个头部获得类型
+    ///
+    /// 空切片只能解码为空的顶层结构体，其余类型报`Empty input`
+    fn take_type(&mut self) -> Result<u8> {
+        match self.current_type.take() {
+            Some(typ) => Ok(typ),
+            None if self.start_len == Some(0) && self.peeked_header.is_none() => {
+                Err(Error::Message("Empty input".into()))
+            }
+            None => Ok(self.next_header()?.1),
+        }
     }
-}
 
-impl<'de, 'a, R: Read> de::SeqAccess<'de> for SeqAccessor<'a, R> {
-    type Error = Error;
+    /// 读容器或字节数组的长度，负数视为数据错误
+    fn get_length(&mut self) -> Result<usize> {
+        let len = self.get_raw_number()?;
+        usize::try_from(len).map_err(|_| Error::Message(format!("Invalid length {}", len)))
+    }
 
-    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
-    where
-        T: de::DeserializeSeed<'de>,
-    {
-        if self.current >= self.len {
-            return Ok(None);
+    /// 读整型，不消耗tag
+    fn get_number(&mut self) -> Result<i64> {
+        let typ = self.take_type()?;
+        match typ {
+            0..=3 | 12 => self.read_number(typ),
+            _ => Err(Error::UnexpectedType {
+                expected: 0,
+                found: typ,
+            }),
         }
+    }
 
-        let (_, typ) = self.de.next_header()?;
+    /// 按类型读整型数据，调用方需保证typ为整型
+    fn read_number(&mut self, typ: u8) -> Result<i64> {
+        raw::read_int(&mut self.reader, typ)
+    }
+}
 
-        self.de.current_type = Some(typ);
+/// 见[`Deserializer::field_iter`]，出错后不再产生字段
+pub struct FieldIter<'a, R> {
+    de: &'a mut Deserializer<R>,
+    done: bool,
+}
 
-        let value = seed.deserialize(&mut *self.de)?;
-        self.current += 1;
+impl<'de, R: JceInput<'de>> Iterator for FieldIter<'_, R> {
+    type Item = Result<(u8, Value)>;
 
-        Ok(Some(value))
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = match self.de.next_header_or_eof() {
+            Ok(Some((tag, typ))) if typ != 11 => {
+                self.de.deserialize_any_value(typ).map(|v| (tag, v))
+            }
+            Ok(_) => {
+                self.done = true;
+                return None;
+            }
+            Err(e) => Err(e),
+        };
+        self.done = result.is_err();
+        Some(result)
     }
 }
 
-impl<'de, 'a, R: Read> de::MapAccess<'de> for MapAccessor<'a, R> {
-    type Error = Error;
+/// 可试探解码的反序列化器，解码失败时回退到解码前的位置，便于依次尝试多个候选类型
+pub struct PeekableDeserializer<R> {
+    de: Deserializer<Rewind<R>>,
+}
 
-    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
-    where
-        K: de::DeserializeSeed<'de>,
-    {
-        if self.current >= self.len {
-            return Ok(None);
+impl<R: std::io::Read> PeekableDeserializer<R> {
+    pub fn new(reader: R) -> Self {
+        PeekableDeserializer {
+            de: Deserializer::new(Rewind::new(reader)),
         }
+    }
+
+    /// 尝试解码为`T`，失败时输入回退，可继续尝试其他类型
+    pub fn try_decode<T: de::DeserializeOwned>(&mut self) -> Result<T> {
+        self.de.reader.checkpoint();
+        let peeked_header = self.de.peeked_header;
+        let current_type = self.de.current_type;
+        let result = T::deserialize(&mut self.de);
+        if result.is_err() {
+            self.de.reader.rewind();
+            self.de.peeked_header = peeked_header;
+            self.de.current_type = current_type;
+        }
+        result
+    }
+
+    /// 取回输入，包括回退后尚未重新读取的字节
+    pub fn into_inner(self) -> std::io::Chain<std::io::Cursor<Vec<u8>>, R> {
+        self.de.reader.into_inner()
+    }
+}
+
+impl Deserializer<&[u8]> {
+    /// 校验剩余数据均为规范化编码，见[`crate::verify_canonical`]
+    pub fn verify_canonical(&mut self) -> Result<()> {
+        while !self.reader.is_empty() {
+            let (_, typ) = self.canonical_header()?;
+            self.canonical_value(typ)?;
+        }
+        Ok(())
+    }
+
+    fn not_canonical(what: &str) -> Error {
+        Error::Message(format!("Not canonical: {}", what))
+    }
+
+    /// 读头部，返回(tag, 类型)
+    fn canonical_header(&mut self) -> Result<(u8, u8)> {
+        let head = self.read_u8()?;
+        let mut tag = head >> 4;
+        if tag == 15 {
+            tag = self.read_u8()?;
+            if tag < 15 {
+                return Err(Self::not_canonical("extended tag below 15"));
+            }
+        }
+        Ok((tag, head & 0x0F))
+    }
+
+    /// 读list元素、map条目等按位置解析的值的头部，tag必须为expected
+    fn canonical_positional_header(&mut self, expected: u8) -> Result<u8> {
+        let (tag, typ) = self.canonical_header()?;
+        if tag != expected {
+            return Err(Error::Message(format!(
+                "Expected tag {}, found tag {}",
+                expected, tag
+            )));
+        }
+        Ok(typ)
+    }
+
+    fn canonical_length(&mut self) -> Result<u64> {
+        let typ = self.canonical_positional_header(0)?;
+        if !matches!(typ, 0..=3 | 12) {
+            return Err(Error::Message(format!("Expected number type, got {}", typ)));
+        }
+        let rest = self.reader;
+        self.canonical_value(typ)?;
+        let len = Deserializer::new(rest).read_number(typ)?;
+        u64::try_from(len).map_err(|_| Self::not_canonical("negative length"))
+    }
+
+    fn canonical_value(&mut self, typ: u8) -> Result<()> {
+        match typ {
+            0 => {
+                if self.read_u8()? == 0 {
+                    return Err(Self::not_canonical("zero stored as Int1"));
+                }
+            }
+            1 => {
+                let v = self.read_u16()? as i16;
+                if i8::try_from(v).is_ok() {
+                    return Err(Self::not_canonical("Int2 fits in Int1"));
+                }
+            }
+            2 => {
+                let v = self.read_u32()? as i32;
+                if i16::try_from(v).is_ok() {
+                    return Err(Self::not_canonical("Int4 fits in Int2"));
+                }
+            }
+            3 => {
+                let v = self.read_u64()? as i64;
+                if i32::try_from(v).is_ok() {
+                    return Err(Self::not_canonical("Int8 fits in Int4"));
+                }
+            }
+            7 => {
+                let len = self.read_u32()? as u64;
+                if len <= 0xFF {
+                    return Err(Self::not_canonical("short string stored as String4"));
+                }
+                self.ignore_bytes(len)?;
+            }
+            8 => {
+                let len = self.canonical_length()?;
+                let mut prev: Option<&[u8]> = None;
+                for _ in 0..len {
+                    let start = self.reader;
+                    let typ = self.canonical_positional_header(0)?;
+                    self.canonical_value(typ)?;
+                    let key = &start[..start.len() - self.reader.len()];
+                    if prev.is_some_and(|p| p >= key) {
+                        return Err(Self::not_canonical("map keys out of order"));
+                    }
+                    prev = Some(key);
+                    let typ = self.canonical_positional_header(1)?;
+                    self.canonical_value(typ)?;
+                }
+            }
+            9 => {
+                let len = self.canonical_length()?;
+                for _ in 0..len {
+                    let typ = self.canonical_positional_header(0)?;
+                    self.canonical_value(typ)?;
+                }
+            }
+            10 => loop {
+                let (_, typ) = self.canonical_header()?;
+                if typ == 11 {
+                    break;
+                }
+                self.canonical_value(typ)?;
+            },
+            13 => {
+                self.canonical_positional_header(0)?;
+                let len = self.canonical_length()?;
+                self.ignore_bytes(len)?;
+            }
+            // 结束符只能出现在结构体体中，由上面的循环消耗
+            11 => return Err(Error::Message("Unexpected struct end".into())),
+            _ => self.skip_type(typ)?,
+        }
+        Ok(())
+    }
+}
+
+macro_rules! deserialize_tag_as_int {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: de::Visitor<'de>,
+            {
+                visitor.visit_u8(self.0)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for TagIdentifier {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.1 {
+            Some(name) => visitor.visit_str(name),
+            None => visitor.visit_string(self.0.to_string()),
+        }
+    }
+
+    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_any(visitor)
+    }
+
+    // 结构体解码为以tag为key的map时，key可以是整型
+    deserialize_tag_as_int! {
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i128 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum ignored_any
+    }
+}
+
+impl<'de, 'a, R: JceInput<'de>> de::SeqAccess<'de> for SeqAccessor<'a, R> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        if self.current >= self.len {
+            return Ok(None);
+        }
+
+        let typ = if self.until_end {
+            match self.de.next_header_or_eof()? {
+                Some((0, typ)) if typ != 11 => typ,
+                Some((tag, typ)) => {
+                    self.de.peek_header(tag, typ);
+                    return Ok(None);
+                }
+                None => return Ok(None),
+            }
+        } else {
+            self.de.next_header()?.1
+        };
+
+        self.de.current_type = Some(typ);
+        self.de.positional = self.de.config.positional_none;
+        self.de.struct_field = false;
+
+        let value = seed.deserialize(&mut *self.de)?;
+        self.current += 1;
+
+        Ok(Some(value))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        if self.until_end {
+            return None;
+        }
+        Some(self.len - self.current)
+    }
+}
+
+impl<'de, 'a, R: JceInput<'de>> de::MapAccess<'de> for MapAccessor<'a, R> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        if self.current >= self.len {
+            return Ok(None);
+        }
+
+        let (_, typ) = self.de.next_header()?;
+        self.de.current_type = Some(typ);
+        self.de.positional = self.de.config.positional_none;
+        self.de.struct_field = false;
 
-        let (_, typ) = self.de.next_header()?;
-        self.de.current_type = Some(typ);
-
         seed.deserialize(&mut *self.de).map(Some)
     }
 
@@ -735,11 +2330,17 @@ impl<'de, 'a, R: Read> de::MapAccess<'de> for MapAccessor<'a, R> {
     {
         let (_, typ) = self.de.next_header()?;
         self.de.current_type = Some(typ);
+        self.de.positional = self.de.config.positional_none;
+        self.de.struct_field = false;
         let val = seed.deserialize(&mut *self.de)?;
 
         self.current += 1;
         Ok(val)
     }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len - self.current)
+    }
 }
 
 #[test]
@@ -781,3 +2382,2220 @@ fn test_struct() -> Result<()> {
     println!("{:?}", crate::from_slice_to_value(&serialized));
     Ok(())
 }
+
+#[test]
+fn test_float_from_int() -> Result<()> {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize)]
+    struct Encoded {
+        #[serde(rename = "1")]
+        value: i16,
+        #[serde(rename = "2")]
+        zero: i16,
+    }
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Decoded {
+        #[serde(rename = "1")]
+        value: f64,
+        #[serde(rename = "2")]
+        zero: f32,
+    }
+
+    let serialized = crate::to_vec(&Encoded {
+        value: 1000,
+        zero: 0,
+    })?;
+    // tag 1, Int2
+    assert_eq!(serialized[0], 0x11);
+    let decoded = crate::from_slice::<Decoded>(&serialized)?;
+    assert_eq!(
+        decoded,
+        Decoded {
+            value: 1000.0,
+            zero: 0.0
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn test_from_slice_field() -> Result<()> {
+    use serde::Serialize;
+    use std::collections::HashMap;
+
+    #[derive(Serialize)]
+    struct Inner {
+        #[serde(rename = "1")]
+        data1: u32,
+        #[serde(rename = "234", with = "serde_bytes")]
+        data2: Vec<u8>,
+    }
+    #[derive(Serialize)]
+    struct Outer {
+        #[serde(rename = "1")]
+        data1: u64,
+        #[serde(rename = "3")]
+        struc: Inner,
+        #[serde(rename = "4")]
+        list: Vec<u16>,
+        #[serde(rename = "5")]
+        map: HashMap<String, u32>,
+        #[serde(rename = "2")]
+        data2: String,
+    }
+
+    let outer = Outer {
+        data1: 1234,
+        struc: Inner {
+            data1: 0xDEADBEEF,
+            data2: vec![1, 2, 3, 4],
+        },
+        list: vec![0xFFF, 0xFFE],
+        map: HashMap::from([("1".to_string(), 1)]),
+        data2: "Test".to_string(),
+    };
+    let serialized = crate::to_vec(&outer)?;
+
+    let data2 = crate::from_slice_field::<String>(&serialized, 2)?;
+    assert_eq!(data2.as_deref(), Some("Test"));
+    assert_eq!(crate::from_slice_field::<String>(&serialized, 9)?, None);
+    // 截断的输入报错，不当作字段缺失
+    assert!(crate::from_slice_field::<i32>(&[0xF0], 3).is_err());
+    assert!(crate::from_slice_field::<String>(&serialized[..serialized.len() - 1], 9).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_borrowed_bytes() -> Result<()> {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Deserialize, Serialize, Debug)]
+    struct Borrowed<'a> {
+        #[serde(rename = "1")]
+        id: u32,
+        #[serde(rename = "2", with = "serde_bytes", borrow)]
+        data: &'a [u8],
+    }
+
+    let payload = [0xAAu8; 32];
+    let serialized = crate::to_vec(&Borrowed {
+        id: 7,
+        data: &payload,
+    })?;
+    let decoded = crate::from_slice::<Borrowed>(&serialized)?;
+    assert_eq!(decoded.data, &payload[..]);
+    let range = serialized.as_ptr_range();
+    assert!(range.contains(&decoded.data.as_ptr()));
+    Ok(())
+}
+
+#[test]
+fn test_value_type() {
+    let cases = [
+        (Value::Byte(1), 0, "Int1"),
+        (Value::Int16(1), 1, "Int2"),
+        (Value::Int32(1), 2, "Int4"),
+        (Value::Int64(1), 3, "Int8"),
+        (Value::Float(1.0), 4, "Float"),
+        (Value::Double(1.0), 5, "Double"),
+        (Value::String("a".into()), 6, "String1"),
+        (Value::String("a".repeat(256)), 7, "String4"),
+        (Value::Map(vec![]), 8, "Map"),
+        (Value::List(vec![]), 9, "List"),
+        (Value::Struct(Default::default()), 10, "StructBegin"),
+        (Value::Zero, 12, "Zero"),
+        (Value::Bytes(vec![]), 13, "SimpleList"),
+    ];
+    for (value, typ, name) in cases {
+        assert_eq!(value.jce_type(), typ);
+        assert_eq!(value.type_name(), name);
+    }
+}
+
+#[test]
+fn test_smart_pointers() -> Result<()> {
+    use serde::{Deserialize, Serialize};
+    use std::rc::Rc;
+    use std::sync::Arc;
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    struct Inner {
+        #[serde(rename = "0")]
+        id: u32,
+    }
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    struct Pointers {
+        #[serde(rename = "1")]
+        boxed: Box<Inner>,
+        #[serde(rename = "2")]
+        shared: Arc<String>,
+        #[serde(rename = "3")]
+        counted: Rc<Vec<u8>>,
+    }
+
+    let value = Pointers {
+        boxed: Box::new(Inner { id: 42 }),
+        shared: Arc::new("shared".into()),
+        counted: Rc::new(vec![1, 2, 3]),
+    };
+    let serialized = crate::to_vec(&value)?;
+    assert_eq!(crate::from_slice::<Pointers>(&serialized)?, value);
+    Ok(())
+}
+
+#[test]
+fn test_skip_unterminated_struct() {
+    // tag 1 结构体内含 tag 0 = 5，缺少结束符0x0B
+    let data = [0x1A, 0x00, 0x05];
+    let err = crate::from_slice_field::<u32>(&data, 2).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "JCE Error: Unterminated struct while skipping (depth 1)"
+    );
+}
+
+#[test]
+fn test_struct_as_map() -> Result<()> {
+    use serde::Serialize;
+    use std::collections::{BTreeMap, HashMap};
+
+    #[derive(Serialize)]
+    struct Inner {
+        #[serde(rename = "1")]
+        data1: u32,
+        #[serde(rename = "2")]
+        data2: u32,
+    }
+    #[derive(Serialize)]
+    struct Outer {
+        #[serde(rename = "1")]
+        data1: u64,
+        #[serde(rename = "3")]
+        struc: Inner,
+    }
+    #[derive(serde::Deserialize, Debug)]
+    struct Decoded {
+        #[serde(rename = "3")]
+        struc: HashMap<u8, u32>,
+    }
+
+    let serialized = crate::to_vec(&Outer {
+        data1: 1234,
+        struc: Inner {
+            data1: 0xDEAD,
+            data2: 7,
+        },
+    })?;
+
+    let decoded = crate::from_slice::<Decoded>(&serialized)?;
+    assert_eq!(decoded.struc, HashMap::from([(1, 0xDEAD), (2, 7)]));
+
+    let root = crate::from_slice::<BTreeMap<u8, Value>>(&serialized)?;
+    assert_eq!(root.keys().copied().collect::<Vec<_>>(), [1, 3]);
+    assert_eq!(root[&1].as_i64(), Some(1234));
+    let Value::Struct(inner) = &root[&3] else {
+        panic!("expected struct, got {:?}", root[&3]);
+    };
+    assert_eq!(inner[&1].as_i64(), Some(0xDEAD));
+    assert_eq!(inner[&2].as_i64(), Some(7));
+
+    // tag 0的字段是map时，顶层仍按结构体解析，不会把该字段当作整个输入
+    #[derive(Serialize)]
+    struct MapFirst {
+        #[serde(rename = "0")]
+        map: BTreeMap<u8, u8>,
+        #[serde(rename = "1")]
+        after: u8,
+    }
+    let serialized = crate::to_vec(&MapFirst {
+        map: BTreeMap::from([(1, 2)]),
+        after: 3,
+    })?;
+    let root = crate::from_slice::<BTreeMap<u8, Value>>(&serialized)?;
+    assert_eq!(root.keys().copied().collect::<Vec<_>>(), [0, 1]);
+    assert!(matches!(&root[&0], Value::Map(entries) if entries.len() == 1));
+    assert_eq!(root[&1].as_i64(), Some(3));
+    Ok(())
+}
+
+#[test]
+fn test_from_slice_seed() -> Result<()> {
+    use std::collections::HashSet;
+    use std::rc::Rc;
+
+    #[derive(serde::Serialize)]
+    struct Names {
+        #[serde(rename = "1")]
+        a: String,
+        #[serde(rename = "2")]
+        b: String,
+        #[serde(rename = "3")]
+        c: String,
+    }
+
+    /// 解码结构体中的所有字符串字段，相同内容共享同一份`Rc<str>`
+    struct Interner<'a>(&'a mut HashSet<Rc<str>>);
+
+    impl<'de> DeserializeSeed<'de> for Interner<'_> {
+        type Value = Vec<Rc<str>>;
+
+        fn deserialize<D>(self, deserializer: D) -> std::result::Result<Self::Value, D::Error>
+        where
+            D: de::Deserializer<'de>,
+        {
+            deserializer.deserialize_map(self)
+        }
+    }
+
+    impl<'de> de::Visitor<'de> for Interner<'_> {
+        type Value = Vec<Rc<str>>;
+
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.write_str("a struct of strings")
+        }
+
+        fn visit_map<A>(self, mut map: A) -> std::result::Result<Self::Value, A::Error>
+        where
+            A: de::MapAccess<'de>,
+        {
+            let mut out = Vec::new();
+            while let Some((_, s)) = map.next_entry::<u8, String>()? {
+                let interned = match self.0.get(s.as_str()) {
+                    Some(rc) => rc.clone(),
+                    None => {
+                        let rc: Rc<str> = s.into();
+                        self.0.insert(rc.clone());
+                        rc
+                    }
+                };
+                out.push(interned);
+            }
+            Ok(out)
+        }
+    }
+
+    let serialized = crate::to_vec(&Names {
+        a: "same".into(),
+        b: "other".into(),
+        c: "same".into(),
+    })?;
+    let mut pool = HashSet::new();
+    let names = crate::from_slice_seed(&serialized, Interner(&mut pool))?;
+    assert_eq!(names.len(), 3);
+    assert!(Rc::ptr_eq(&names[0], &names[2]));
+    assert_eq!(pool.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_double_to_f32() -> Result<()> {
+    use serde::Deserialize;
+
+    #[derive(serde::Serialize)]
+    struct Wide {
+        #[serde(rename = "0")]
+        v: f64,
+    }
+    #[derive(Deserialize, Debug)]
+    struct Narrow {
+        #[serde(rename = "0")]
+        v: f32,
+    }
+
+    let serialized = crate::to_vec(&Wide { v: 1e300 })?;
+    assert!(crate::from_slice::<Narrow>(&serialized).is_err());
+
+    let mut de = Deserializer::from_slice(&serialized).with_coercion(Coercion::Lenient);
+    let narrow = Narrow::deserialize(&mut de)?;
+    assert_eq!(narrow.v, f32::INFINITY);
+    Ok(())
+}
+
+#[test]
+fn test_struct_keyed_map() -> Result<()> {
+    use std::collections::HashMap;
+
+    #[derive(serde::Serialize, PartialEq, Eq, Hash)]
+    struct Point {
+        #[serde(rename = "0")]
+        x: i32,
+        #[serde(rename = "1")]
+        y: i32,
+    }
+    #[derive(serde::Serialize)]
+    struct Grid {
+        #[serde(rename = "0")]
+        cells: HashMap<Point, String>,
+    }
+
+    let grid = Grid {
+        cells: HashMap::from([
+            (Point { x: 1, y: 2 }, "a".to_string()),
+            (Point { x: -3, y: 400 }, "b".to_string()),
+        ]),
+    };
+    let serialized = crate::to_vec(&grid)?;
+    let mut root = crate::from_slice_to_value(&serialized)?;
+    let cells = root.remove(&0).and_then(Value::into_key_map).unwrap();
+
+    let key = JceKey(Value::Struct(
+        [(0, Value::Byte(1)), (1, Value::Byte(2))].into(),
+    ));
+    assert_eq!(cells.len(), 2);
+    assert!(matches!(&cells[&key], Value::String(s) if s == "a"));
+
+    // 直接解码为以JceKey为key的map
+    #[derive(serde::Deserialize)]
+    struct Decoded {
+        #[serde(rename = "0")]
+        cells: HashMap<JceKey, Value>,
+    }
+    let decoded = crate::from_slice::<Decoded>(&serialized)?;
+    assert_eq!(decoded.cells.len(), 2);
+    assert!(matches!(&decoded.cells[&key], Value::String(s) if s == "a"));
+    let key = JceKey(Value::Struct(
+        [(0, Value::Byte(0xFD)), (1, Value::Int16(400))].into(),
+    ));
+    assert!(matches!(&decoded.cells[&key], Value::String(s) if s == "b"));
+    Ok(())
+}
+
+#[test]
+fn test_max_known_tag() -> Result<()> {
+    use serde::Deserialize;
+
+    #[derive(serde::Serialize)]
+    struct Newer {
+        #[serde(rename = "0")]
+        id: u32,
+        #[serde(rename = "50")]
+        added: String,
+    }
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Older {
+        #[serde(rename = "0")]
+        id: u32,
+    }
+
+    let serialized = crate::to_vec(&Newer {
+        id: 3,
+        added: "new".into(),
+    })?;
+
+    let mut strict = Deserializer::from_slice(&serialized).with_max_known_tag(10);
+    assert!(Older::deserialize(&mut strict).is_err());
+
+    let mut lenient = Deserializer::from_slice(&serialized)
+        .with_max_known_tag(10)
+        .with_coercion(Coercion::Lenient);
+    assert_eq!(Older::deserialize(&mut lenient)?, Older { id: 3 });
+    Ok(())
+}
+
+#[test]
+fn test_value_normalize() -> Result<()> {
+    let a = Value::Struct(
+        [
+            (0, Value::Int32(5)),
+            (
+                1,
+                Value::Map(vec![
+                    (Value::String("b".into()), Value::Int64(-300)),
+                    (Value::String("a".into()), Value::Zero),
+                ]),
+            ),
+        ]
+        .into(),
+    );
+    let b = Value::Struct(
+        [
+            (0, Value::Byte(5)),
+            (
+                1,
+                Value::Map(vec![
+                    (Value::String("a".into()), Value::Byte(0)),
+                    (Value::String("b".into()), Value::Int16(-300)),
+                ]),
+            ),
+        ]
+        .into(),
+    );
+
+    let bytes = a.canonical_bytes()?;
+    assert_eq!(bytes, b.canonical_bytes()?);
+    assert!(crate::verify_canonical(&bytes));
+    assert!(matches!(Value::Int64(0).normalize(), Value::Zero));
+    Ok(())
+}
+
+#[test]
+fn test_char() -> Result<()> {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    struct Chars {
+        #[serde(rename = "0")]
+        ascii: char,
+        #[serde(rename = "1")]
+        latin: char,
+        #[serde(rename = "2")]
+        cjk: char,
+        #[serde(rename = "3")]
+        emoji: char,
+    }
+
+    let chars = Chars {
+        ascii: 'a',
+        latin: 'é',
+        cjk: '中',
+        emoji: '🦀',
+    };
+    let serialized = crate::to_vec(&chars)?;
+    assert_eq!(crate::from_slice::<Chars>(&serialized)?, chars);
+
+    #[derive(Serialize)]
+    struct Text {
+        #[serde(rename = "0")]
+        v: &'static str,
+    }
+    #[derive(Deserialize, Debug)]
+    struct One {
+        #[serde(rename = "0")]
+        v: char,
+    }
+    assert_eq!(
+        crate::from_slice::<One>(&crate::to_vec(&Text { v: "中" })?)?.v,
+        '中'
+    );
+    assert!(crate::from_slice::<One>(&crate::to_vec(&Text { v: "ab" })?).is_err());
+
+    #[derive(Serialize)]
+    struct Surrogate {
+        #[serde(rename = "0")]
+        v: u32,
+    }
+    assert!(crate::from_slice::<One>(&crate::to_vec(&Surrogate { v: 0xD800 })?).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_simple_list_element_type() -> Result<()> {
+    use serde::Deserialize;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Data {
+        #[serde(rename = "0", with = "serde_bytes")]
+        v: Vec<u8>,
+    }
+    // tag 0 SimpleList, 元素类型错标为Zero, 长度2
+    let buf = [0x0D, 0x0C, 0x00, 0x02, 0xAB, 0xCD];
+    let err = crate::from_slice::<Data>(&buf).unwrap_err().to_string();
+    assert!(err.contains("found 12 (Zero)"), "{}", err);
+
+    let mut de = Deserializer::from_slice(&buf).with_coercion(Coercion::Lenient);
+    assert_eq!(
+        Data::deserialize(&mut de)?,
+        Data {
+            v: vec![0xAB, 0xCD]
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn test_peekable_try_decode() -> Result<()> {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Named {
+        #[serde(rename = "0")]
+        name: String,
+    }
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    struct Numbered {
+        #[serde(rename = "0")]
+        id: i32,
+        #[serde(rename = "1")]
+        list: Vec<u16>,
+    }
+    #[derive(Debug, PartialEq)]
+    enum Either {
+        Named(Named),
+        Numbered(Numbered),
+    }
+
+    let value = Numbered {
+        id: 7,
+        list: vec![1, 300],
+    };
+    let serialized = crate::to_vec(&value)?;
+    let mut de = PeekableDeserializer::new(&serialized[..]);
+    let decoded = de
+        .try_decode::<Named>()
+        .map(Either::Named)
+        .or_else(|_| de.try_decode::<Numbered>().map(Either::Numbered))?;
+    assert_eq!(decoded, Either::Numbered(value));
+
+    // 解码失败回退后取回输入，已读出的字节仍在其中
+    let mut de = PeekableDeserializer::new(&serialized[..]);
+    assert!(de.try_decode::<Named>().is_err());
+    let mut rest = Vec::new();
+    std::io::Read::read_to_end(&mut de.into_inner(), &mut rest)?;
+    assert_eq!(rest, serialized);
+    Ok(())
+}
+
+#[test]
+fn test_negative_ints() -> Result<()> {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    struct Signed {
+        #[serde(rename = "0")]
+        a: i8,
+        #[serde(rename = "1")]
+        b: i16,
+        #[serde(rename = "2")]
+        c: i32,
+        #[serde(rename = "3")]
+        d: i64,
+        #[serde(rename = "4")]
+        widened: i64,
+    }
+
+    let value = Signed {
+        a: -1,
+        b: -400,
+        c: -100_000,
+        d: i64::MIN,
+        widened: -2,
+    };
+    let serialized = crate::to_vec(&value)?;
+    // 负数同样按最短宽度写入
+    assert_eq!(&serialized[..4], &[0x00, 0xFF, 0x11, 0xFE]);
+    assert_eq!(crate::from_slice::<Signed>(&serialized)?, value);
+
+    let fields = crate::from_slice_to_value(&serialized)?;
+    assert_eq!(fields[&1].as_i64(), Some(-400));
+    assert_eq!(fields[&2].as_i64(), Some(-100_000));
+    assert_eq!(fields[&3].as_i64(), Some(i64::MIN));
+    assert_eq!(fields[&4].as_i64(), Some(-2));
+    Ok(())
+}
+
+#[test]
+fn test_expect_tag() -> Result<()> {
+    use serde::Deserialize;
+
+    let mut buf = Vec::new();
+    raw::write_int(&mut buf, 0, 100_000)?;
+    raw::write_string(&mut buf, 3, "tail")?;
+
+    let mut de = Deserializer::from_slice(&buf);
+    let err = de.expect_tag(1).unwrap_err().to_string();
+    assert!(err.contains("Expected tag 1, found tag 0"), "{}", err);
+    assert_eq!(de.expect_tag(0)?, 2);
+    assert_eq!(i32::deserialize(&mut de)?, 100_000);
+    assert_eq!(de.expect_tag(3)?, 6);
+    assert_eq!(String::deserialize(&mut de)?, "tail");
+    Ok(())
+}
+
+#[test]
+fn test_value_from() {
+    let cases = [
+        (Value::from(-1i8), 0, -1),
+        (Value::from(-2i16), 1, -2),
+        (Value::from(42), 2, 42),
+        (Value::from(i64::MIN), 3, i64::MIN),
+        (Value::from(u8::MAX), 1, 255),
+        (Value::from(u16::MAX), 2, 65535),
+        (Value::from(u32::MAX), 3, u32::MAX as i64),
+        (Value::from(true), 0, 1),
+        (Value::from(false), 0, 0),
+    ];
+    for (value, typ, n) in cases {
+        assert_eq!(value.jce_type(), typ, "{:?}", value);
+        assert_eq!(value.as_i64(), Some(n), "{:?}", value);
+    }
+
+    assert!(matches!(Value::from(1.5f32), Value::Float(v) if v == 1.5));
+    assert!(matches!(Value::from(2.5f64), Value::Double(v) if v == 2.5));
+    assert!(matches!(Value::from("a"), Value::String(s) if s == "a"));
+    assert!(matches!(Value::from(String::from("b")), Value::String(s) if s == "b"));
+    assert!(matches!(Value::from(vec![1u8, 2]), Value::Bytes(b) if b == [1, 2]));
+    assert!(matches!(
+        Value::from(vec![Value::from(1), Value::from("x")]),
+        Value::List(items) if items.len() == 2
+    ));
+}
+
+#[test]
+fn test_forged_skip_length() -> Result<()> {
+    use serde::Deserialize;
+    use std::io::Read;
+
+    #[derive(Deserialize, Debug)]
+    struct Known {
+        #[serde(rename = "0", default)]
+        _id: i32,
+    }
+    // tag 5 String4, 长度0xFFFFFFF0，但后面没有对应的数据
+    let forged = [0x57, 0xFF, 0xFF, 0xFF, 0xF0, 0x00];
+
+    let err = crate::from_slice::<Known>(&forged).unwrap_err().to_string();
+    assert!(err.contains("exceeds remaining input"), "{}", err);
+
+    // 无法得知剩余长度的输入按max_alloc限制，不会真的去读4GB
+    let endless = (&forged[..]).chain(std::io::repeat(0));
+    let mut de = Deserializer::new(endless).with_max_alloc(1024);
+    let err = Known::deserialize(&mut de).unwrap_err().to_string();
+    assert!(err.contains("exceeds max_alloc 1024"), "{}", err);
+    Ok(())
+}
+
+#[test]
+fn test_value_deserialize() -> Result<()> {
+    use serde::Serialize;
+    use std::collections::HashMap;
+
+    let list = crate::from_slice::<Value>(&crate::to_vec(&vec![1i32, -300, 70_000])?)?;
+    let Value::List(items) = list else {
+        panic!("expected a list, got {:?}", list);
+    };
+    let items: Vec<_> = items.iter().map(|v| v.as_i64()).collect();
+    assert_eq!(items, [Some(1), Some(-300), Some(70_000)]);
+
+    let int = crate::from_slice::<Value>(&crate::to_vec(&100_000i32)?)?;
+    assert!(matches!(int, Value::Int32(100_000)), "{:?}", int);
+
+    #[derive(Serialize)]
+    struct Sample {
+        #[serde(rename = "0")]
+        id: i16,
+        #[serde(rename = "2")]
+        name: &'static str,
+        #[serde(rename = "3")]
+        scores: HashMap<String, u8>,
+    }
+    let sample = Sample {
+        id: -2,
+        name: "jce",
+        scores: HashMap::from([("a".to_string(), 1)]),
+    };
+    let serialized = crate::to_vec(&sample)?;
+    let Value::Struct(fields) = crate::from_slice::<Value>(&serialized)? else {
+        panic!("expected a struct");
+    };
+    assert_eq!(fields[&0].as_i64(), Some(-2));
+    assert!(matches!(&fields[&2], Value::String(s) if s == "jce"));
+    assert!(matches!(&fields[&3], Value::Map(entries) if entries.len() == 1));
+    let mut reencoded = Vec::new();
+    raw::write_fields(&mut reencoded, &fields)?;
+    assert_eq!(reencoded, serialized);
+    Ok(())
+}
+
+#[test]
+fn test_internally_tagged_enum() -> Result<()> {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Detail {
+        #[serde(rename = "0")]
+        level: u8,
+    }
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    #[serde(tag = "0")]
+    enum Message {
+        Text {
+            #[serde(rename = "1")]
+            body: String,
+        },
+        Ping {
+            #[serde(rename = "1")]
+            seq: u32,
+            #[serde(rename = "2")]
+            detail: Detail,
+            #[serde(rename = "3")]
+            hops: Vec<i16>,
+        },
+    }
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Envelope {
+        #[serde(rename = "5")]
+        message: Message,
+    }
+
+    let messages = [
+        Message::Text { body: "hi".into() },
+        Message::Ping {
+            seq: 70_000,
+            detail: Detail { level: 3 },
+            hops: vec![-1, 300],
+        },
+    ];
+    for message in messages {
+        let serialized = crate::to_vec(&message)?;
+        // 判别字段以变体名写在tag 0
+        assert_eq!(serialized[0], 0x06);
+        assert_eq!(crate::from_slice::<Message>(&serialized)?, message);
+
+        let envelope = Envelope { message };
+        let serialized = crate::to_vec(&envelope)?;
+        assert_eq!(crate::from_slice::<Envelope>(&serialized)?, envelope);
+    }
+
+    // 判别字段也可以是变体序号
+    let mut buf = Vec::new();
+    raw::write_int(&mut buf, 0, 0)?;
+    raw::write_string(&mut buf, 1, "by index")?;
+    assert_eq!(
+        crate::from_slice::<Message>(&buf)?,
+        Message::Text {
+            body: "by index".into()
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn test_unbounded_list() -> Result<()> {
+    use serde::Deserialize;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Inner {
+        #[serde(rename = "0")]
+        items: Vec<i32>,
+    }
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Outer {
+        #[serde(rename = "1")]
+        inner: Inner,
+        #[serde(rename = "2")]
+        after: u8,
+    }
+
+    // tag 1结构体中的tag 0列表长度为Zero，实际有三个元素，直到结构体结束符
+    let mut buf = vec![0x1A, 0x09, 0x0C];
+    raw::write_int(&mut buf, 0, 5)?;
+    raw::write_int(&mut buf, 0, -6)?;
+    raw::write_int(&mut buf, 0, 70_000)?;
+    raw::write_struct_end(&mut buf)?;
+    raw::write_int(&mut buf, 2, 9)?;
+
+    assert!(crate::from_slice::<Outer>(&buf).is_err());
+
+    let mut de = Deserializer::from_slice(&buf).with_unbounded_lists(true);
+    assert_eq!(
+        Outer::deserialize(&mut de)?,
+        Outer {
+            inner: Inner {
+                items: vec![5, -6, 70_000]
+            },
+            after: 9,
+        }
+    );
+
+    // 标准编码的空列表（包括列表元素中的空列表）不受影响
+    #[derive(serde::Serialize, Deserialize, Debug, PartialEq)]
+    struct Nested {
+        #[serde(rename = "0")]
+        v: Vec<Vec<i32>>,
+        #[serde(rename = "1")]
+        x: u8,
+        #[serde(rename = "2")]
+        empty: Vec<i32>,
+    }
+    let nested = Nested {
+        v: vec![vec![], vec![1]],
+        x: 5,
+        empty: vec![],
+    };
+    let buf = crate::to_vec(&nested)?;
+    let mut de = Deserializer::from_slice(&buf).with_unbounded_lists(true);
+    assert_eq!(Nested::deserialize(&mut de)?, nested);
+    Ok(())
+}
+
+#[test]
+fn test_top_level_int() -> Result<()> {
+    assert_eq!(
+        crate::from_slice::<u32>(&crate::to_vec(&70_000u32)?)?,
+        70_000
+    );
+    assert_eq!(crate::from_slice::<u32>(&crate::to_vec(&0u32)?)?, 0);
+    assert_eq!(crate::from_slice::<i8>(&crate::to_vec(&-3i8)?)?, -3);
+    assert_eq!(
+        crate::from_slice::<i64>(&crate::to_vec(&i64::MIN)?)?,
+        i64::MIN
+    );
+    assert!(crate::from_slice::<bool>(&crate::to_vec(&true)?)?);
+    assert_eq!(
+        crate::from_reader::<u16, _>(&[0x01, 0x12, 0x34][..])?,
+        0x1234
+    );
+    Ok(())
+}
+
+#[test]
+fn test_top_level_terminator() -> Result<()> {
+    use serde::Deserialize;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Message {
+        #[serde(rename = "0")]
+        id: u8,
+        #[serde(rename = "1")]
+        name: String,
+    }
+    let mut body = Vec::new();
+    raw::write_int(&mut body, 0, 1)?;
+    raw::write_string(&mut body, 1, "a")?;
+    let mut terminated = body.clone();
+    raw::write_struct_end(&mut terminated)?;
+
+    let expected = Message {
+        id: 1,
+        name: "a".into(),
+    };
+    assert_eq!(crate::from_slice::<Message>(&body)?, expected);
+    assert_eq!(crate::from_slice::<Message>(&terminated)?, expected);
+    assert_eq!(crate::from_slice_to_value(&body)?.len(), 2);
+    assert_eq!(crate::from_slice_to_value(&terminated)?.len(), 2);
+
+    // 结束符之后还有数据
+    let mut stray = Vec::new();
+    raw::write_int(&mut stray, 0, 1)?;
+    raw::write_struct_end(&mut stray)?;
+    raw::write_string(&mut stray, 1, "a")?;
+    assert!(crate::from_slice::<Message>(&stray).is_err());
+    assert!(crate::from_slice_to_value(&stray).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_read_next_value() -> Result<()> {
+    let mut buf = Vec::new();
+    raw::write_int(&mut buf, 0, -7)?;
+    raw::write_string(&mut buf, 3, "walk")?;
+    raw::write_struct_begin(&mut buf, 20)?;
+    raw::write_int(&mut buf, 1, 1)?;
+    raw::write_struct_end(&mut buf)?;
+
+    let mut de = Deserializer::from_slice(&buf);
+    let mut fields = Vec::new();
+    for _ in 0..3 {
+        fields.push(de.read_next_value()?);
+    }
+    assert!(de.read_next_value().is_err());
+
+    let tags: Vec<u8> = fields.iter().map(|(tag, _)| *tag).collect();
+    assert_eq!(tags, [0, 3, 20]);
+    assert_eq!(fields[0].1.as_i64(), Some(-7));
+    assert!(matches!(&fields[1].1, Value::String(s) if s == "walk"));
+    assert!(matches!(&fields[2].1, Value::Struct(inner) if inner[&1].as_i64() == Some(1)));
+    Ok(())
+}
+
+#[test]
+fn test_u64_range() -> Result<()> {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    struct Wide {
+        #[serde(rename = "0")]
+        v: u64,
+    }
+
+    let boundaries = [
+        0,
+        1,
+        u8::MAX as u64,
+        u32::MAX as u64,
+        i64::MAX as u64 - 1,
+        i64::MAX as u64,
+        i64::MAX as u64 + 1,
+        u64::MAX - 1,
+        u64::MAX,
+    ];
+    for v in boundaries {
+        let serialized = crate::to_vec(&Wide { v })?;
+        assert_eq!(crate::from_slice::<Wide>(&serialized)?, Wide { v }, "{}", v);
+    }
+
+    // 高位为1的值按i64的位模式写入，再压缩为最短宽度: u64::MAX即-1，写为Int1
+    let serialized = crate::to_vec(&Wide { v: u64::MAX })?;
+    assert_eq!(serialized, [0x00, 0xFF]);
+    let serialized = crate::to_vec(&Wide { v: 1 << 63 })?;
+    assert_eq!(serialized, [0x03, 0x80, 0, 0, 0, 0, 0, 0, 0]);
+    Ok(())
+}
+
+#[test]
+fn test_size_hint() -> Result<()> {
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
+
+    #[derive(Deserialize, Serialize)]
+    struct Large {
+        #[serde(rename = "0")]
+        list: Vec<u32>,
+        #[serde(rename = "1")]
+        map: HashMap<u16, u8>,
+    }
+    let large = Large {
+        list: (0..1000).collect(),
+        map: (0..300).map(|i| (i, 1)).collect(),
+    };
+    let serialized = crate::to_vec(&large)?;
+    let decoded = crate::from_slice::<Large>(&serialized)?;
+    // 按长度一次分配，没有经过倍增扩容
+    assert_eq!(decoded.list.capacity(), 1000);
+    assert_eq!(decoded.map.len(), 300);
+
+    struct Hint(Option<usize>);
+    impl<'de> Deserialize<'de> for Hint {
+        fn deserialize<D: de::Deserializer<'de>>(d: D) -> std::result::Result<Self, D::Error> {
+            struct HintVisitor;
+            impl<'de> de::Visitor<'de> for HintVisitor {
+                type Value = Hint;
+                fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    f.write_str("a map")
+                }
+                fn visit_map<A: de::MapAccess<'de>>(
+                    self,
+                    mut map: A,
+                ) -> std::result::Result<Hint, A::Error> {
+                    let hint = map.size_hint();
+                    while map
+                        .next_entry::<de::IgnoredAny, de::IgnoredAny>()?
+                        .is_some()
+                    {}
+                    Ok(Hint(hint))
+                }
+            }
+            d.deserialize_map(HintVisitor)
+        }
+    }
+    #[derive(Deserialize)]
+    struct MapHint {
+        #[serde(rename = "1")]
+        map: Hint,
+    }
+    assert_eq!(crate::from_slice::<MapHint>(&serialized)?.map.0, Some(300));
+    Ok(())
+}
+
+#[test]
+fn test_max_collection_len() -> Result<()> {
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    struct Limited {
+        #[serde(rename = "0", with = "serde_bytes")]
+        bytes: Vec<u8>,
+        #[serde(rename = "1")]
+        list: Vec<u8>,
+        #[serde(rename = "2")]
+        map: HashMap<u8, u8>,
+    }
+    let decode = |value: &Limited, max_alloc: usize, max_len: usize| -> Result<Limited> {
+        let serialized = crate::to_vec(value)?;
+        let mut de = Deserializer::from_slice(&serialized)
+            .with_max_alloc(max_alloc)
+            .with_max_collection_len(max_len);
+        Limited::deserialize(&mut de)
+    };
+
+    let value = Limited {
+        bytes: vec![0; 64],
+        list: vec![1; 8],
+        map: HashMap::from([(1, 1)]),
+    };
+    assert_eq!(decode(&value, 64, 8)?, value);
+
+    // 字节数组只受max_alloc限制
+    let err = decode(&value, 63, 8).unwrap_err().to_string();
+    assert!(err.contains("exceeds max_alloc 63"), "{}", err);
+    let err = decode(&value, 64, 7).unwrap_err().to_string();
+    assert!(err.contains("exceeds max_collection_len 7"), "{}", err);
+
+    let big_map = Limited {
+        bytes: vec![],
+        list: vec![],
+        map: (0..10).map(|i| (i, i)).collect(),
+    };
+    let err = decode(&big_map, 1024, 9).unwrap_err().to_string();
+    assert!(
+        err.contains("Collection length 10 exceeds max_collection_len 9"),
+        "{}",
+        err
+    );
+    Ok(())
+}
+
+#[test]
+fn test_positional_none() -> Result<()> {
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    struct Optionals {
+        #[serde(rename = "0")]
+        list: Vec<Option<u32>>,
+        #[serde(rename = "1")]
+        map: HashMap<String, Option<u32>>,
+        #[serde(rename = "2")]
+        field: Option<u32>,
+        #[serde(rename = "3")]
+        zero: Option<u32>,
+    }
+
+    let value = Optionals {
+        list: vec![Some(1), None, Some(0), Some(70_000), None],
+        map: HashMap::from([
+            ("none".to_string(), None),
+            ("zero".to_string(), Some(0)),
+            ("five".to_string(), Some(5)),
+        ]),
+        field: None,
+        zero: Some(0),
+    };
+    let mut serialized = Vec::new();
+    let mut ser = crate::ser::Serializer::new(&mut serialized).with_positional_none(true);
+    value.serialize(&mut ser)?;
+    // 列表长度5，None写为Zero，Some(0)写为Int1
+    assert_eq!(
+        &serialized[..10],
+        &[0x09, 0x00, 0x05, 0x00, 0x01, 0x0C, 0x00, 0x00, 0x02, 0x00]
+    );
+    let mut de = Deserializer::from_slice(&serialized).with_positional_none(true);
+    assert_eq!(Optionals::deserialize(&mut de)?, value);
+    // 结构体字段的None不写入，Some(0)仍为Zero
+    assert_eq!(&serialized[serialized.len() - 1..], &[0x3C]);
+
+    // 默认不开启: Some(0)为Zero，规范化输出可通过校验
+    assert_eq!(crate::to_vec(&vec![Some(0u32)])?, [0x09, 0x00, 0x01, 0x0C]);
+    assert!(crate::verify_canonical(&crate::to_vec_canonical(&vec![
+        Some(0u32)
+    ])?));
+
+    // 只作用于Some直接包裹的标量，不影响其中容器的长度与结构体字段
+    #[derive(Serialize)]
+    struct In {
+        #[serde(rename = "0")]
+        a: u32,
+    }
+    let mut nested = Vec::new();
+    let mut ser = crate::ser::Serializer::new(&mut nested).with_positional_none(true);
+    (vec![Some(Vec::<u32>::new())], vec![Some(In { a: 0 })]).serialize(&mut ser)?;
+    assert_eq!(
+        nested,
+        [
+            0x09, 0x00, 0x02, 0x09, 0x00, 0x01, 0x09, 0x0C, 0x09, 0x00, 0x01, 0x0A, 0x0C, 0x0B
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_validate() -> Result<()> {
+    use std::collections::BTreeMap;
+
+    let fields = BTreeMap::from([
+        (0, Value::Int32(70_000)),
+        (1, Value::String("hello".into())),
+        (
+            2,
+            Value::List(vec![Value::Bytes(vec![1, 2, 3]), Value::Zero]),
+        ),
+        (3, Value::Struct(BTreeMap::from([(0, Value::Byte(1))]))),
+    ]);
+    let mut buf = Vec::new();
+    raw::write_fields(&mut buf, &fields)?;
+    crate::validate(&buf)?;
+
+    // 截断在字段之间仍是合法的结构体体，截断在字段内部则报错
+    crate::validate(&buf[..5])?;
+    assert!(crate::validate(&buf[..8]).is_err());
+    assert!(crate::validate(&buf[..buf.len() - 1]).is_err());
+    // 顶层结束符之后不能再有数据
+    buf.extend_from_slice(&[0x0B, 0x0C]);
+    assert!(crate::validate(&buf).is_err());
+
+    // 负数长度的list/map与超过剩余输入的长度按解码时的规则报错
+    for data in [
+        &[0x09, 0x00, 0xFF][..],
+        &[0x08, 0x00, 0xFF],
+        &[0x09, 0x00, 0x05],
+    ] {
+        assert!(crate::validate(data).is_err(), "{:02x?}", data);
+        assert!(crate::from_slice::<Value>(data).is_err(), "{:02x?}", data);
+    }
+    let err = crate::validate(&[0x09, 0x00, 0xFF]).unwrap_err();
+    assert!(err.to_string().contains("Invalid length -1"), "{}", err);
+    Ok(())
+}
+
+#[test]
+fn test_unknown_type_policy() -> Result<()> {
+    // tag 0: Int1 1, tag 1: 未知类型14, tag 2: Int1 2
+    let data = [0x00, 0x01, 0x1E, 0x00, 0x20, 0x02];
+
+    let mut de = Deserializer::from_slice(&data);
+    let err = de.validate().unwrap_err();
+    assert!(err.to_string().contains("at offset 2"), "{}", err);
+
+    let mut de = Deserializer::from_slice(&data).with_unknown_type_policy(UnknownTypePolicy::Stop);
+    de.validate()?;
+    assert!(de.stopped_at_unknown_type());
+
+    // 嵌套在列表中的未知类型同样停止
+    let data = [0x09, 0x00, 0x02, 0x00, 0x01, 0x0E, 0x00];
+    let mut de = Deserializer::from_slice(&data).with_unknown_type_policy(UnknownTypePolicy::Stop);
+    de.validate()?;
+    assert!(de.stopped_at_unknown_type());
+    assert!(Deserializer::from_slice(&data).validate().is_err());
+    Ok(())
+}
+
+#[test]
+fn test_value_visit_mut() -> Result<()> {
+    use std::collections::BTreeMap;
+
+    let inner = Value::Struct(BTreeMap::from([(0, Value::from("secret"))]));
+    let fields = BTreeMap::from([
+        (0, Value::from(1i32)),
+        (1, Value::from("token")),
+        (2, Value::List(vec![inner.clone(), Value::from("x")])),
+    ]);
+    let mut buf = Vec::new();
+    raw::write_fields(&mut buf, &fields)?;
+
+    let mut value = Value::Struct(crate::from_slice_to_value(&buf)?);
+    value.map_fields(|tag, v| {
+        if tag == 1 {
+            *v = Value::Zero;
+        }
+    });
+    // 递归替换所有字符串
+    let mut strings = 0;
+    value.visit_mut(|v| {
+        if let Value::String(s) = v {
+            strings += 1;
+            s.clear();
+        }
+    });
+    assert_eq!(strings, 2);
+
+    let Value::Struct(fields) = value else {
+        unreachable!()
+    };
+    let mut rewritten = Vec::new();
+    raw::write_fields(&mut rewritten, &fields)?;
+    let decoded = crate::from_slice_to_value(&rewritten)?;
+    assert!(matches!(decoded[&1], Value::Zero));
+    let Value::List(items) = &decoded[&2] else {
+        panic!("expected list")
+    };
+    assert!(matches!(&items[1], Value::String(s) if s.is_empty()));
+    Ok(())
+}
+
+#[test]
+fn test_tuple_len() -> Result<()> {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    struct Pair {
+        #[serde(rename = "0")]
+        pair: (u32, String),
+        #[serde(rename = "1")]
+        empty: [u32; 0],
+        #[serde(rename = "2")]
+        after: u8,
+    }
+
+    let value = Pair {
+        pair: (7, "seven".into()),
+        empty: [],
+        after: 9,
+    };
+    let serialized = crate::to_vec(&value)?;
+    assert_eq!(crate::from_slice::<Pair>(&serialized)?, value);
+
+    #[derive(Deserialize, Serialize)]
+    struct Lists {
+        #[serde(rename = "0")]
+        list: Vec<u32>,
+    }
+    #[derive(Deserialize, Debug)]
+    struct Triple {
+        #[serde(rename = "0")]
+        _triple: (u32, u32, u32),
+    }
+    #[derive(Deserialize, Debug)]
+    struct Empty {
+        #[serde(rename = "0")]
+        _empty: [u32; 0],
+    }
+
+    // 元素个数多于或少于元组长度都报错，而不是留下未读的元素
+    for list in [vec![1, 2], vec![1, 2, 3, 4]] {
+        let serialized = crate::to_vec(&Lists { list })?;
+        let err = crate::from_slice::<Triple>(&serialized).unwrap_err();
+        assert!(err.to_string().contains("a list of 3 elements"), "{}", err);
+    }
+    let serialized = crate::to_vec(&Lists { list: vec![1] })?;
+    assert!(crate::from_slice::<Empty>(&serialized).is_err());
+    let serialized = crate::to_vec(&Lists { list: vec![] })?;
+    crate::from_slice::<Empty>(&serialized)?;
+    Ok(())
+}
+
+#[test]
+fn test_required_fields() -> Result<()> {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Request {
+        #[serde(rename = "0")]
+        id: u32,
+        #[serde(rename = "1")]
+        name: Option<String>,
+        #[serde(rename = "2", default)]
+        retries: u8,
+    }
+
+    #[derive(Serialize)]
+    struct Partial {
+        #[serde(rename = "0")]
+        id: Option<u32>,
+        #[serde(rename = "1")]
+        name: Option<String>,
+    }
+
+    // optional字段缺失时为None或默认值
+    let serialized = crate::to_vec(&Partial {
+        id: Some(5),
+        name: None,
+    })?;
+    assert_eq!(
+        crate::from_slice::<Request>(&serialized)?,
+        Request {
+            id: 5,
+            name: None,
+            retries: 0
+        }
+    );
+
+    // require字段缺失时报错
+    let serialized = crate::to_vec(&Partial {
+        id: None,
+        name: Some("a".into()),
+    })?;
+    let err = crate::from_slice::<Request>(&serialized).unwrap_err();
+    assert!(err.to_string().contains("missing field `0`"), "{}", err);
+    Ok(())
+}
+
+#[test]
+fn test_enum_map_keys() -> Result<()> {
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
+    enum Channel {
+        Web,
+        Mobile,
+        Desktop,
+    }
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    struct Stats {
+        #[serde(rename = "0")]
+        counts: HashMap<Channel, u32>,
+        #[serde(rename = "1")]
+        last: Channel,
+    }
+
+    let value = Stats {
+        counts: HashMap::from([
+            (Channel::Web, 3),
+            (Channel::Mobile, 0),
+            (Channel::Desktop, 70_000),
+        ]),
+        last: Channel::Mobile,
+    };
+    let serialized = crate::to_vec(&value)?;
+    // 单元变体为tag 0的结构体，只含变体序号
+    assert!(serialized.windows(4).any(|w| w == [0x0A, 0x00, 0x01, 0x0B]));
+    assert_eq!(crate::from_slice::<Stats>(&serialized)?, value);
+
+    let canonical = crate::to_vec_canonical(&value)?;
+    assert_eq!(crate::from_slice::<Stats>(&canonical)?, value);
+    Ok(())
+}
+
+#[test]
+fn test_field_iter() -> Result<()> {
+    use std::collections::BTreeMap;
+
+    let inner = BTreeMap::from([(0, Value::from(7i32)), (3, Value::from("name"))]);
+    let fields = BTreeMap::from([(0, Value::Struct(inner)), (1, Value::from(9i32))]);
+    let mut buf = Vec::new();
+    raw::write_fields(&mut buf, &fields)?;
+
+    let mut de = Deserializer::from_slice(&buf);
+    assert_eq!(de.next_header()?, (0, 10));
+    // 只读到嵌套结构体的结束符，之后的字段留给调用方
+    let nested = de.field_iter().collect::<Result<Vec<_>>>()?;
+    assert_eq!(nested.len(), 2);
+    assert_eq!((nested[0].0, nested[0].1.as_i64()), (0, Some(7)));
+    assert!(matches!(&nested[1], (3, Value::String(s)) if s == "name"));
+
+    let rest = de.field_iter().collect::<Result<Vec<_>>>()?;
+    assert_eq!(rest.len(), 1);
+    assert_eq!((rest[0].0, rest[0].1.as_i64()), (1, Some(9)));
+
+    // 结构体未结束时报错
+    let mut de = Deserializer::from_slice(&buf[..4]);
+    de.next_header()?;
+    assert!(de.field_iter().any(|r| r.is_err()));
+    Ok(())
+}
+
+#[test]
+fn test_from_slice_partial() -> Result<()> {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    struct Frame {
+        #[serde(rename = "0")]
+        seq: u32,
+        #[serde(rename = "1")]
+        body: String,
+    }
+
+    let first = Frame {
+        seq: 1,
+        body: "first".into(),
+    };
+    let second = Frame {
+        seq: 2,
+        body: "second".into(),
+    };
+    let mut buf = crate::to_vec(&first)?;
+    buf.push(0x0B);
+    let boundary = buf.len();
+    buf.extend(crate::to_vec(&second)?);
+
+    let (decoded, consumed) = crate::from_slice_partial::<Frame>(&buf)?;
+    assert_eq!(decoded, first);
+    assert_eq!(consumed, boundary);
+    let (decoded, consumed) = crate::from_slice_partial::<Frame>(&buf[consumed..])?;
+    assert_eq!(decoded, second);
+    assert_eq!(consumed, buf.len() - boundary);
+
+    // 默认仍不允许结束符之后还有数据
+    assert!(crate::from_slice::<Frame>(&buf).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_map_vec_duplicates() -> Result<()> {
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
+
+    #[derive(Deserialize, Serialize, Debug)]
+    struct Headers {
+        #[serde(rename = "0")]
+        entries: JceMapVec<String, u32>,
+    }
+
+    #[derive(Deserialize, Debug)]
+    struct Collapsed {
+        #[serde(rename = "0")]
+        entries: HashMap<String, u32>,
+    }
+
+    let mut buf = Vec::new();
+    raw::write_map_begin(&mut buf, 0, 3)?;
+    for (k, v) in [("a", 1), ("b", 2), ("a", 3)] {
+        raw::write_string(&mut buf, 0, k)?;
+        raw::write_int(&mut buf, 1, v)?;
+    }
+
+    let headers = crate::from_slice::<Headers>(&buf)?;
+    assert_eq!(
+        headers.entries.0,
+        [("a".into(), 1), ("b".into(), 2), ("a".into(), 3)]
+    );
+    // 同样的数据解码为HashMap时重复的key被合并
+    let collapsed = crate::from_slice::<Collapsed>(&buf)?;
+    assert_eq!(collapsed.entries.len(), 2);
+
+    // 重新编码时保留原有顺序与重复的key
+    assert_eq!(crate::to_vec(&headers)?, buf);
+    Ok(())
+}
+
+#[test]
+fn test_zero_as_json() -> Result<()> {
+    let mut buf = Vec::new();
+    raw::write_int(&mut buf, 0, 0)?;
+    raw::write_string(&mut buf, 1, "a")?;
+    assert_eq!(buf[0], 0x0C);
+
+    let json = crate::from_slice::<serde_json::Value>(&buf)?;
+    assert_eq!(json, serde_json::json!({"0": 0, "1": "a"}));
+    let value = crate::from_slice::<Value>(&buf)?;
+    let Value::Struct(fields) = value else {
+        panic!("expected struct")
+    };
+    assert!(matches!(fields[&0], Value::Zero));
+    Ok(())
+}
+
+#[test]
+fn test_deque_and_linked_list() -> Result<()> {
+    use serde::{Deserialize, Serialize};
+    use std::collections::{LinkedList, VecDeque};
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    struct Queues {
+        #[serde(rename = "0")]
+        deque: VecDeque<u32>,
+        #[serde(rename = "1")]
+        list: LinkedList<String>,
+    }
+
+    // 元素个数超过256，顺序保持不变
+    let mut deque: VecDeque<u32> = (0..1000).collect();
+    deque.rotate_left(300);
+    let value = Queues {
+        deque,
+        list: ["c", "a", "b"].into_iter().map(String::from).collect(),
+    };
+    let serialized = crate::to_vec(&value)?;
+    assert_eq!(crate::from_slice::<Queues>(&serialized)?, value);
+    Ok(())
+}
+
+#[test]
+fn test_missing_terminator() -> Result<()> {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    struct Inner {
+        #[serde(rename = "0")]
+        a: u32,
+        #[serde(rename = "1")]
+        b: String,
+    }
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    struct Outer {
+        #[serde(rename = "0")]
+        id: u8,
+        #[serde(rename = "1")]
+        inner: Inner,
+    }
+
+    let value = Outer {
+        id: 1,
+        inner: Inner {
+            a: 7,
+            b: "x".into(),
+        },
+    };
+    let serialized = crate::to_vec(&value)?;
+    assert_eq!(serialized.last(), Some(&0x0B));
+    let truncated = &serialized[..serialized.len() - 1];
+
+    // 默认严格，截断的嵌套结构体报错
+    let err = crate::from_slice::<Outer>(truncated).unwrap_err();
+    assert!(
+        err.to_string().contains("Missing struct terminator"),
+        "{}",
+        err
+    );
+    assert!(crate::from_slice::<Value>(truncated).is_err());
+    assert!(crate::validate(truncated).is_err());
+
+    let mut de = Deserializer::from_slice(truncated).with_allow_missing_terminator(true);
+    assert_eq!(Outer::deserialize(&mut de)?, value);
+    let mut de = Deserializer::from_slice(truncated).with_allow_missing_terminator(true);
+    de.validate()?;
+    Ok(())
+}
+
+#[test]
+fn test_top_level_list() -> Result<()> {
+    let list = vec![1u32, 70_000, 0];
+    let serialized = crate::to_vec(&list)?;
+    assert_eq!(serialized[..3], [0x09, 0x00, 0x03]);
+    assert_eq!(crate::from_slice::<Vec<u32>>(&serialized)?, list);
+
+    let root = crate::from_slice_to_value(&serialized)?;
+    assert!(matches!(&root[&0], Value::List(items) if items.len() == 3));
+
+    let strings = vec!["a".to_string(), "bc".to_string()];
+    let serialized = crate::to_vec(&strings)?;
+    assert_eq!(crate::from_slice::<Vec<String>>(&serialized)?, strings);
+    assert_eq!(crate::from_slice::<String>(&crate::to_vec(&"top")?)?, "top");
+    assert_eq!(
+        crate::from_slice::<(u8, String)>(&crate::to_vec(&(1u8, "x"))?)?,
+        (1, "x".into())
+    );
+    Ok(())
+}
+
+#[test]
+fn test_unexpected_type() -> Result<()> {
+    use serde::Deserialize;
+
+    #[derive(Deserialize, Debug)]
+    struct Id {
+        #[serde(rename = "0")]
+        _id: u32,
+    }
+
+    #[derive(Deserialize, Debug)]
+    struct Name {
+        #[serde(rename = "0")]
+        _name: String,
+    }
+
+    let mut string = Vec::new();
+    raw::write_string(&mut string, 0, "abc")?;
+    let err = crate::from_slice::<Id>(&string).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            Error::UnexpectedType {
+                expected: 0,
+                found: 6
+            }
+        ),
+        "{:?}",
+        err
+    );
+    assert_eq!(
+        err.to_string(),
+        "JCE Error: Expected type 0 (Int1), found 6 (String1)"
+    );
+
+    let mut int = Vec::new();
+    raw::write_int(&mut int, 0, 70_000)?;
+    let err = crate::from_slice::<Name>(&int).unwrap_err();
+    assert!(matches!(
+        err,
+        Error::UnexpectedType {
+            expected: 6,
+            found: 2
+        }
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_deserialize_struct_raw() -> Result<()> {
+    let mut buf = Vec::new();
+    raw::write_int(&mut buf, 1, 5)?;
+    raw::write_string(&mut buf, 0, "a")?;
+    raw::write_int(&mut buf, 1, 6)?;
+
+    let fields = Deserializer::from_slice(&buf).deserialize_struct_raw()?;
+    let tags: Vec<_> = fields.iter().map(|(tag, _)| *tag).collect();
+    assert_eq!(tags, [1, 0, 1]);
+    assert_eq!(fields[0].1.as_i64(), Some(5));
+    assert_eq!(fields[2].1.as_i64(), Some(6));
+
+    // deserialize_all只保留最后一个
+    let all = crate::from_slice_to_value(&buf)?;
+    assert_eq!(all.len(), 2);
+    assert_eq!(all[&1].as_i64(), Some(6));
+    Ok(())
+}
+
+#[test]
+fn test_top_level_float() -> Result<()> {
+    let serialized = crate::to_vec(&1.5f64)?;
+    assert_eq!(serialized[0], 0x05);
+    assert_eq!(crate::from_slice::<f64>(&serialized)?, 1.5);
+    assert_eq!(crate::from_slice::<f32>(&crate::to_vec(&-2.25f32)?)?, -2.25);
+    // 整数值的浮点数可能按整型编码
+    assert_eq!(crate::from_slice::<f64>(&[0x00, 0x03])?, 3.0);
+    Ok(())
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn test_tracing_events() -> Result<()> {
+    use serde::{Deserialize, Serialize};
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata};
+
+    /// 记录span名称与字段事件中的tag
+    #[derive(Default, Clone)]
+    struct Recorder {
+        spans: Arc<Mutex<Vec<&'static str>>>,
+        tags: Arc<Mutex<Vec<u64>>>,
+    }
+
+    struct TagVisitor<'a>(&'a Mutex<Vec<u64>>);
+
+    impl Visit for TagVisitor<'_> {
+        fn record_u64(&mut self, field: &Field, value: u64) {
+            if field.name() == "tag" {
+                self.0.lock().unwrap().push(value);
+            }
+        }
+        fn record_debug(&mut self, _: &Field, _: &dyn std::fmt::Debug) {}
+    }
+
+    impl tracing::Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut spans = self.spans.lock().unwrap();
+            spans.push(span.metadata().name());
+            Id::from_u64(spans.len() as u64)
+        }
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, event: &Event<'_>) {
+            event.record(&mut TagVisitor(&self.tags));
+        }
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+    }
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    struct Packet {
+        #[serde(rename = "0")]
+        id: u32,
+        #[serde(rename = "2")]
+        items: Vec<u8>,
+    }
+
+    let value = Packet {
+        id: 1,
+        items: vec![1, 2],
+    };
+    let serialized = crate::to_vec(&value)?;
+    let recorder = Recorder::default();
+    let decoded = tracing::subscriber::with_default(recorder.clone(), || {
+        crate::from_slice::<Packet>(&serialized)
+    })?;
+    assert_eq!(decoded, value);
+    assert_eq!(*recorder.spans.lock().unwrap(), ["jce_struct", "jce_list"]);
+    assert_eq!(*recorder.tags.lock().unwrap(), [0, 2]);
+    Ok(())
+}
+
+#[test]
+fn test_simple_list_element_type_wrapper() -> Result<()> {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    struct Blob {
+        #[serde(rename = "1")]
+        list: SimpleList,
+        #[serde(rename = "2", with = "serde_bytes")]
+        standard: Vec<u8>,
+    }
+
+    let value = Blob {
+        list: SimpleList {
+            element_type: 1,
+            data: vec![0xAB, 0xCD],
+        },
+        standard: vec![1],
+    };
+    let serialized = crate::to_vec(&value)?;
+    // tag 1 SimpleList, 元素类型1 (Int2), 长度2
+    assert_eq!(serialized[..6], [0x1D, 0x01, 0x00, 0x02, 0xAB, 0xCD]);
+    // 标准的字节数组元素类型仍为0
+    assert_eq!(serialized[6..], [0x2D, 0x00, 0x00, 0x01, 0x01]);
+    assert_eq!(crate::from_slice::<Blob>(&serialized)?, value);
+
+    // 元素类型不是合法的JCE类型时报错，不写入任何内容
+    let mut buf = Vec::new();
+    let invalid = SimpleList {
+        element_type: 16,
+        data: vec![1],
+    };
+    let err = invalid
+        .serialize(&mut crate::ser::Serializer::new(&mut buf))
+        .unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("Invalid SimpleList element type 16"),
+        "{}",
+        err
+    );
+    assert!(buf.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_tag_alias() -> Result<()> {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize)]
+    struct V1 {
+        #[serde(rename = "0")]
+        id: u32,
+        #[serde(rename = "3")]
+        name: String,
+    }
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    struct V2 {
+        #[serde(rename = "0")]
+        id: u32,
+        #[serde(rename = "5", alias = "3")]
+        name: String,
+        #[serde(rename = "6", alias = "0x04", default)]
+        note: String,
+    }
+
+    let old = crate::to_vec(&V1 {
+        id: 1,
+        name: "old".into(),
+    })?;
+    let expected = V2 {
+        id: 1,
+        name: "old".into(),
+        note: String::new(),
+    };
+    assert_eq!(crate::from_slice::<V2>(&old)?, expected);
+    // 新数据按新的tag写入
+    let new = crate::to_vec(&expected)?;
+    assert_eq!(new[2], 0x56);
+    assert_eq!(crate::from_slice::<V2>(&new)?, expected);
+
+    let mut hex_alias = old.clone();
+    raw::write_string(&mut hex_alias, 4, "note")?;
+    assert_eq!(crate::from_slice::<V2>(&hex_alias)?.note, "note");
+    Ok(())
+}
+
+#[test]
+fn test_skip_map_len_overflow() -> Result<()> {
+    #[derive(serde::Deserialize, Debug)]
+    struct Empty {}
+
+    for len in [i64::MAX / 2 + 1, i64::MAX] {
+        let mut buf = Vec::new();
+        raw::write_map_begin(&mut buf, 1, 0)?;
+        buf.truncate(1);
+        raw::write_int(&mut buf, 0, len)?;
+        let err = crate::from_slice::<Empty>(&buf).unwrap_err();
+        assert!(err.to_string().contains("Invalid map length"), "{}", err);
+    }
+
+    // 不溢出时按正常流程在输入结束处报错
+    let mut buf = Vec::new();
+    raw::write_map_begin(&mut buf, 1, 0)?;
+    buf.truncate(1);
+    raw::write_int(&mut buf, 0, i64::MAX / 2)?;
+    assert!(crate::from_slice::<Empty>(&buf).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_value_to_json_like_string() {
+    let value = Value::Struct(std::collections::BTreeMap::from([
+        (0, Value::Int32(1)),
+        (1, Value::String("a\"b\n".into())),
+        (2, Value::Bytes(vec![0xDE, 0xAD, 0x01])),
+        (3, Value::List(vec![Value::Zero, Value::Byte(0xFF)])),
+        (
+            4,
+            Value::Map(vec![
+                (Value::String("k".into()), Value::Double(1.5)),
+                (Value::Int16(7), Value::Float(f32::NAN)),
+            ]),
+        ),
+        (
+            15,
+            Value::Struct(std::collections::BTreeMap::from([(0, Value::Int64(-2))])),
+        ),
+    ]));
+    assert_eq!(
+        value.to_json_like_string(),
+        r#"{"0":1,"1":"a\"b\n","2":"dead01","3":[0,-1],"4":{"k":1.5,"7":null},"15":{"0":-2}}"#
+    );
+}
+
+#[test]
+fn test_skip_current_value() -> Result<()> {
+    use serde::Deserialize;
+
+    let mut buf = Vec::new();
+    raw::write_int(&mut buf, 0, 7)?;
+    raw::write_list_begin(&mut buf, 1, 2)?;
+    raw::write_string(&mut buf, 0, "skipped")?;
+    raw::write_int(&mut buf, 0, 1)?;
+    raw::write_struct_begin(&mut buf, 2)?;
+    raw::write_int(&mut buf, 0, 1)?;
+    raw::write_struct_end(&mut buf)?;
+    raw::write_string(&mut buf, 3, "kept")?;
+
+    let mut de = Deserializer::from_slice(&buf);
+    de.expect_tag(0)?;
+    assert_eq!(i32::deserialize(&mut de)?, 7);
+    assert_eq!(de.expect_tag(1)?, 9);
+    de.skip_current_value()?;
+    // 未读头部时先读头部再跳过
+    de.skip_current_value()?;
+    de.expect_tag(3)?;
+    assert_eq!(String::deserialize(&mut de)?, "kept");
+    Ok(())
+}
+
+#[test]
+fn test_boxed_slices() -> Result<()> {
+    use std::rc::Rc;
+    use std::sync::Arc;
+
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Slices<'a> {
+        #[serde(rename = "0")]
+        ids: Box<[u32]>,
+        #[serde(rename = "1")]
+        names: Rc<[String]>,
+        #[serde(rename = "2")]
+        scores: Arc<[i64]>,
+        #[serde(rename = "3", borrow)]
+        tags: Box<[&'a str]>,
+        #[serde(rename = "4", with = "serde_bytes")]
+        data: Box<[u8]>,
+        #[serde(rename = "5")]
+        empty: Box<[u32]>,
+    }
+
+    let value = Slices {
+        ids: vec![1, 70_000, u32::MAX].into(),
+        names: vec!["a".to_string(), "b".repeat(300)].into(),
+        scores: vec![-1, i64::MAX].into(),
+        tags: vec!["x", "y"].into(),
+        data: vec![1, 2, 3].into(),
+        empty: Box::new([]),
+    };
+    let buf = crate::to_vec(&value)?;
+    assert_eq!(crate::from_slice::<Slices>(&buf)?, value);
+
+    let top = crate::to_vec(&vec![5u32, 6])?;
+    assert_eq!(*crate::from_slice::<Box<[u32]>>(&top)?, [5, 6]);
+    assert_eq!(*crate::from_slice::<Rc<[u32]>>(&top)?, [5, 6]);
+    Ok(())
+}
+
+#[test]
+fn test_empty_input() -> Result<()> {
+    fn empty_err<T: de::DeserializeOwned>() -> String {
+        match crate::from_slice::<T>(&[]) {
+            Ok(_) => panic!("empty input decoded as {}", std::any::type_name::<T>()),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    assert_eq!(empty_err::<i32>(), "JCE Error: Empty input");
+    assert_eq!(empty_err::<f64>(), "JCE Error: Empty input");
+    assert_eq!(empty_err::<String>(), "JCE Error: Empty input");
+    assert_eq!(empty_err::<Vec<u8>>(), "JCE Error: Empty input");
+    assert_eq!(
+        empty_err::<serde_bytes::ByteBuf>(),
+        "JCE Error: Empty input"
+    );
+    assert!(crate::from_slice_partial::<u8>(&[]).is_err());
+
+    // 顶层结构体没有头部，空输入即没有字段的结构体
+    #[derive(serde::Deserialize)]
+    struct Required {
+        #[serde(rename = "0")]
+        _id: i32,
+    }
+    assert!(empty_err::<Required>().contains("missing field"));
+    assert!(crate::from_slice_to_value(&[])?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_zero_into_all_int_widths() -> Result<()> {
+    #[derive(serde::Deserialize, Debug, PartialEq, Default)]
+    struct Ints {
+        #[serde(rename = "0")]
+        a: i8,
+        #[serde(rename = "1")]
+        b: i16,
+        #[serde(rename = "2")]
+        c: i32,
+        #[serde(rename = "3")]
+        d: i64,
+        #[serde(rename = "4")]
+        e: u8,
+        #[serde(rename = "5")]
+        f: u16,
+        #[serde(rename = "6")]
+        g: u32,
+        #[serde(rename = "7")]
+        h: u64,
+        #[serde(rename = "8")]
+        i: i128,
+        #[serde(rename = "9")]
+        j: u128,
+    }
+
+    let mut buf = Vec::new();
+    for tag in 0..10 {
+        raw::write_header(&mut buf, tag, 12)?;
+    }
+    assert_eq!(crate::from_slice::<Ints>(&buf)?, Ints::default());
+
+    // 顶层与列表元素
+    assert_eq!(crate::from_slice::<i8>(&[0x0C])?, 0);
+    assert_eq!(crate::from_slice::<u64>(&[0x0C])?, 0);
+    assert_eq!(
+        crate::from_slice::<Vec<u16>>(&[0x09, 0x00, 2, 0x0C, 0x0C])?,
+        [0, 0]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_ignored_any_contexts() -> Result<()> {
+    use serde::de::IgnoredAny;
+    use std::collections::BTreeMap;
+
+    #[derive(serde::Serialize)]
+    struct Full {
+        #[serde(rename = "0")]
+        id: i32,
+        #[serde(rename = "1")]
+        list: Vec<(String, Vec<u8>)>,
+        #[serde(rename = "2")]
+        map: BTreeMap<String, Vec<i64>>,
+        #[serde(rename = "3")]
+        tail: String,
+    }
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    struct Partial {
+        #[serde(rename = "0")]
+        id: i32,
+        #[serde(rename = "1")]
+        list: Vec<IgnoredAny>,
+        #[serde(rename = "2")]
+        map: BTreeMap<String, IgnoredAny>,
+        #[serde(rename = "3")]
+        tail: String,
+    }
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    struct IdOnly {
+        #[serde(rename = "0")]
+        id: i32,
+    }
+
+    let full = Full {
+        id: 9,
+        list: vec![("a".into(), vec![1, 2]), ("b".into(), vec![])],
+        map: [("k".to_string(), vec![1, i64::MAX])].into(),
+        tail: "end".into(),
+    };
+    let buf = crate::to_vec(&full)?;
+
+    // 结构体字段、列表元素与map的值
+    let partial = crate::from_slice::<Partial>(&buf)?;
+    assert_eq!(partial.id, 9);
+    assert_eq!(partial.list.len(), 2);
+    assert_eq!(partial.map.keys().collect::<Vec<_>>(), ["k"]);
+    assert_eq!(partial.tail, "end");
+    assert_eq!(crate::from_slice::<IdOnly>(&buf)?, IdOnly { id: 9 });
+
+    // 顶层没有已读的头部
+    crate::from_slice::<IgnoredAny>(&buf)?;
+    crate::from_slice::<IgnoredAny>(&crate::to_vec(&vec![1, 2])?)?;
+    let mut truncated = buf.clone();
+    truncated.pop();
+    assert!(crate::from_slice::<IgnoredAny>(&truncated).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_map_of_structs() -> Result<()> {
+    use std::collections::HashMap;
+
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Clone)]
+    struct Inner {
+        #[serde(rename = "0")]
+        id: i64,
+        #[serde(rename = "1", default)]
+        note: Option<String>,
+        #[serde(rename = "2")]
+        tags: Vec<String>,
+    }
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Outer {
+        #[serde(rename = "0")]
+        entries: HashMap<String, Inner>,
+        #[serde(rename = "1")]
+        after: i32,
+    }
+
+    let inner = |id: i64| Inner {
+        id,
+        note: (id % 2 == 0).then(|| format!("n{}", id)),
+        tags: vec!["t".into(); id as usize],
+    };
+    let outer = Outer {
+        entries: (0..4).map(|i| (format!("k{}", i), inner(i))).collect(),
+        after: 7,
+    };
+    let buf = crate::to_vec(&outer)?;
+    assert_eq!(crate::from_slice::<Outer>(&buf)?, outer);
+
+    // 手工拼装: 条目的value是tag 1的结构体
+    let mut buf = Vec::new();
+    raw::write_map_begin(&mut buf, 0, 1)?;
+    raw::write_string(&mut buf, 0, "only")?;
+    raw::write_struct_begin(&mut buf, 1)?;
+    raw::write_int(&mut buf, 0, 3)?;
+    raw::write_list_begin(&mut buf, 2, 0)?;
+    raw::write_struct_end(&mut buf)?;
+    // 单独的map是tag 0的字段，按字段取出
+    let decoded = crate::from_slice_field::<HashMap<String, Inner>>(&buf, 0)?.unwrap();
+    assert_eq!(
+        decoded["only"],
+        Inner {
+            id: 3,
+            note: None,
+            tags: vec![],
+        }
+    );
+
+    // value不是结构体时给出类型错误
+    let mut buf = Vec::new();
+    raw::write_map_begin(&mut buf, 0, 1)?;
+    raw::write_string(&mut buf, 0, "bad")?;
+    raw::write_int(&mut buf, 1, 3)?;
+    let err = crate::from_slice::<HashMap<String, Inner>>(&buf).unwrap_err();
+    assert!(
+        matches!(err, Error::UnexpectedType { expected: 10, .. }),
+        "{}",
+        err
+    );
+    Ok(())
+}
+
+#[test]
+fn test_value_semantic_eq() -> Result<()> {
+    assert!(Value::Byte(5).semantic_eq(&Value::Int32(5)));
+    assert!(Value::Int64(5).semantic_eq(&Value::Int16(5)));
+    assert!(Value::Zero.semantic_eq(&Value::Int32(0)));
+    assert!(Value::Byte(0xFF).semantic_eq(&Value::Int64(-1)));
+    assert!(!Value::Byte(5).semantic_eq(&Value::Int32(6)));
+    assert!(!Value::Int32(5).semantic_eq(&Value::Double(5.0)));
+    assert!(!Value::Int32(5).semantic_eq(&Value::String("5".into())));
+    // 结构相等的前提下区分宽度
+    assert!(!key_eq(&Value::Byte(5), &Value::Int32(5)));
+
+    // 按原宽度写入后再以最短形式解码
+    let fields = std::collections::BTreeMap::from([
+        (0, Value::Int32(5)),
+        (1, Value::List(vec![Value::Int64(0), Value::Int16(-2)])),
+        (
+            2,
+            Value::Map(vec![(Value::Int32(1), Value::String("a".into()))]),
+        ),
+    ]);
+    let mut buf = Vec::new();
+    raw::write_fields(&mut buf, &fields)?;
+    let wide = Value::Struct(fields);
+    let minimal = Value::Struct(crate::from_slice_to_value(&wide.canonical_bytes()?)?);
+    let decoded = Value::Struct(crate::from_slice_to_value(&buf)?);
+    assert!(!key_eq(&decoded, &minimal));
+    assert!(decoded.semantic_eq(&minimal));
+    assert!(minimal.semantic_eq(&wide));
+    Ok(())
+}
+
+#[test]
+fn test_collection_len_exceeds_input() -> Result<()> {
+    for typ in [8u8, 9] {
+        let mut buf = vec![typ];
+        raw::write_int(&mut buf, 0, 1 << 61)?;
+        // 切片输入: 长度超过剩余字节数时直接报错
+        let err = crate::from_slice::<Value>(&buf).unwrap_err();
+        assert!(
+            err.to_string().contains("exceeds remaining input"),
+            "{}",
+            err
+        );
+
+        // 长度未知的输入不预分配，在输入结束处报错
+        assert!(crate::from_slice_to_value(&buf).is_err());
+        assert!(crate::from_reader::<Value, _>(&buf[..]).is_err());
+    }
+    Ok(())
+}
+
+#[test]
+fn test_value_nested_struct_then_field() -> Result<()> {
+    // 嵌套结构体的结束符之后紧跟同层的字段，不能被当作结束符的一部分吞掉
+    let mut buf = Vec::new();
+    raw::write_struct_begin(&mut buf, 0)?;
+    raw::write_int(&mut buf, 0, 1)?;
+    raw::write_struct_end(&mut buf)?;
+    raw::write_int(&mut buf, 1, 2)?;
+    raw::write_string(&mut buf, 2, "c")?;
+
+    let root = crate::from_slice_to_value(&buf)?;
+    assert_eq!(root.keys().copied().collect::<Vec<_>>(), [0, 1, 2]);
+    assert_eq!(root[&1].as_i64(), Some(2));
+    assert!(matches!(&root[&2], Value::String(s) if s == "c"));
+    Ok(())
+}
+
+#[test]
+fn test_any_root_borrows() -> Result<()> {
+    struct Borrowed<'a>(&'a str);
+    impl<'de> de::Deserialize<'de> for Borrowed<'de> {
+        fn deserialize<D: de::Deserializer<'de>>(d: D) -> std::result::Result<Self, D::Error> {
+            struct BorrowedVisitor;
+            impl<'de> de::Visitor<'de> for BorrowedVisitor {
+                type Value = Borrowed<'de>;
+                fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    f.write_str("a borrowed string")
+                }
+                fn visit_borrowed_str<E>(self, v: &'de str) -> std::result::Result<Self::Value, E> {
+                    Ok(Borrowed(v))
+                }
+            }
+            d.deserialize_any(BorrowedVisitor)
+        }
+    }
+
+    // 顶层的单个值直接从输入借用，不经过中间的Value
+    let buf = crate::to_vec(&"top level")?;
+    assert_eq!(crate::from_slice::<Borrowed>(&buf)?.0, "top level");
+
+    // 流式输入无法向前查看，tag 0的值按结构体体的字段解析
+    let list = crate::to_vec(&vec![1, 2])?;
+    let value = crate::from_reader::<Value, _>(&list[..])?;
+    assert!(
+        matches!(&value, Value::Struct(fields) if matches!(&fields[&0], Value::List(items) if items.len() == 2)),
+        "{:?}",
+        value
+    );
+    Ok(())
+}
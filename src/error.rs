@@ -7,6 +7,11 @@ pub type Result<T> = std::result::Result<T, Error>;
 pub enum Error {
     Message(String),
     Io(std::io::Error),
+    /// 线上类型与目标类型不匹配；目标接受多种宽度（如整型、字符串）时expected为其中最窄的类型
+    UnexpectedType {
+        expected: u8,
+        found: u8,
+    },
 }
 
 impl ser::Error for Error {
@@ -26,6 +31,14 @@ impl Display for Error {
         match self {
             Error::Message(m) => write!(f, "JCE Error: {}", m),
             Error::Io(e) => write!(f, "IO Error: {}", e),
+            Error::UnexpectedType { expected, found } => write!(
+                f,
+                "JCE Error: Expected type {} ({}), found {} ({})",
+                expected,
+                crate::de::type_name(*expected),
+                found,
+                crate::de::type_name(*found)
+            ),
         }
     }
 }
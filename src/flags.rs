@@ -0,0 +1,74 @@
+//! 整型位标志的编解码，用法: 为标志类型实现[`Flags`]，再以`#[serde(rename = "1", with = "serde_jce::flags")]`标注字段
+//!
+//! 标志按底层整型写入，与普通整型一样使用能容纳该值的最短类型（空标志为Zero）；
+//! 对端要求固定宽度时可配合`Serializer::with_int_width(IntWidth::Native)`按`Bits`的位宽写入
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// 以整型表示的位标志
+pub trait Flags: Sized {
+    /// 底层整型，如`u32`
+    type Bits: Serialize + for<'de> Deserialize<'de>;
+
+    fn bits(&self) -> Self::Bits;
+
+    /// 保留所有位，包括未定义的位
+    fn from_bits_retain(bits: Self::Bits) -> Self;
+}
+
+pub fn serialize<T, S>(flags: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Flags,
+    S: Serializer,
+{
+    flags.bits().serialize(serializer)
+}
+
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: Flags,
+    D: Deserializer<'de>,
+{
+    T::Bits::deserialize(deserializer).map(T::from_bits_retain)
+}
+
+#[test]
+fn test_flags() -> crate::Result<()> {
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Permissions(u16);
+
+    impl Permissions {
+        const READ: Permissions = Permissions(1);
+        const WRITE: Permissions = Permissions(1 << 1);
+        const ADMIN: Permissions = Permissions(1 << 8);
+    }
+
+    impl Flags for Permissions {
+        type Bits = u16;
+
+        fn bits(&self) -> u16 {
+            self.0
+        }
+
+        fn from_bits_retain(bits: u16) -> Self {
+            Permissions(bits)
+        }
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct User {
+        #[serde(rename = "0", with = "crate::flags")]
+        permissions: Permissions,
+        #[serde(rename = "1", with = "crate::flags")]
+        none: Permissions,
+    }
+
+    let user = User {
+        permissions: Permissions(Permissions::READ.0 | Permissions::WRITE.0 | Permissions::ADMIN.0),
+        none: Permissions(0),
+    };
+    let serialized = crate::to_vec(&user)?;
+    // 0x0103写为Int2，空标志为Zero
+    assert_eq!(serialized, [0x01, 0x01, 0x03, 0x1C]);
+    assert_eq!(crate::from_slice::<User>(&serialized)?, user);
+    Ok(())
+}
@@ -1,12 +1,24 @@
+#[cfg(feature = "bytes")]
+pub mod bytes;
+#[cfg(feature = "chrono")]
+pub mod chrono;
+pub mod compat;
 pub mod de;
 pub mod error;
+pub mod flags;
+pub mod net;
+pub mod raw;
+pub mod read;
 pub mod ser;
+#[cfg(feature = "uuid")]
+pub mod uuid;
 
 use std::io::Read;
 
 pub use de::Deserializer;
 pub use error::{Error, Result};
 pub use ser::Serializer;
+use serde::de::{DeserializeOwned, DeserializeSeed};
 use serde::{Deserialize, Serialize};
 
 use crate::de::Value;
@@ -21,25 +33,110 @@ where
     Ok(vec)
 }
 
-pub fn to_writer<W, T>(writer: W, value: &T) -> Result<()>
+/// 以规范化形式序列化，相等的值总是得到相同的字节，可用于签名/哈希
+pub fn to_vec_canonical<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: Serialize,
+{
+    let mut vec = Vec::with_capacity(128);
+    let mut serializer = Serializer::new(&mut vec).with_canonical(true);
+    value.serialize(&mut serializer)?;
+    Ok(vec)
+}
+
+/// 序列化并写入writer，返回前会flush
+///
+/// 数据边序列化边写入（列表元素逐个写出），出错时writer中可能已有部分数据，调用方需自行丢弃
+pub fn to_writer<W, T>(mut writer: W, value: &T) -> Result<()>
 where
     W: std::io::Write,
     T: Serialize,
 {
-    let mut serializer = Serializer::new(writer);
+    let mut serializer = Serializer::new(&mut writer);
     value.serialize(&mut serializer)?;
+    writer.flush()?;
     Ok(())
 }
 
+/// 序列化到调用方提供的定长缓冲区（如栈上的`[u8; N]`），返回写入的字节数，空间不足时报`Buffer full`
+///
+/// 输出本身不分配堆内存；需要缓存的map（排序或长度未知）与长度未知的列表仍会临时分配
+pub fn to_slice<T>(buf: &mut [u8], value: &T) -> Result<usize>
+where
+    T: Serialize,
+{
+    let capacity = buf.len();
+    let mut cursor = &mut buf[..];
+    let mut serializer = Serializer::new(&mut cursor);
+    value.serialize(&mut serializer).map_err(|e| match e {
+        Error::Io(e) if e.kind() == std::io::ErrorKind::WriteZero => {
+            Error::Message(format!("Buffer full: capacity {} bytes", capacity))
+        }
+        e => e,
+    })?;
+    Ok(capacity - cursor.len())
+}
+
+/// 只检查能否序列化（如字段名不是有效的tag），输出直接丢弃，错误与`to_vec`相同
+pub fn validate_serializable<T>(value: &T) -> Result<()>
+where
+    T: Serialize,
+{
+    let mut serializer = Serializer::new(std::io::sink());
+    value.serialize(&mut serializer)
+}
+
+/// 空切片视为没有字段的顶层结构体，解码为其他类型时报`Empty input`
 pub fn from_slice<'a, T>(slice: &'a [u8]) -> Result<T>
 where
     T: Deserialize<'a>,
 {
-    let mut deserializer = Deserializer::new(slice);
+    let mut deserializer = Deserializer::from_slice(slice);
     let t = T::deserialize(&mut deserializer)?;
     Ok(t)
 }
 
+/// 解码开头的一个值，返回该值与消耗的字节数，便于继续解析之后的数据
+///
+/// 顶层结构体需以结束符结尾，否则会一直读到输入结束
+pub fn from_slice_partial<'a, T>(slice: &'a [u8]) -> Result<(T, usize)>
+where
+    T: Deserialize<'a>,
+{
+    let mut deserializer = Deserializer::from_slice(slice).with_trailing_data(true);
+    let t = T::deserialize(&mut deserializer)?;
+    let consumed = deserializer.offset().unwrap_or(slice.len());
+    Ok((t, consumed))
+}
+
+/// 解码顶层结构体，`T`未声明的字段以tag为key原样返回
+///
+/// 基于`#[serde(flatten)]`，`T`的字段名需为十进制tag，字节数组不会借用输入
+pub fn from_slice_all<T>(slice: &[u8]) -> Result<(T, std::collections::BTreeMap<u8, Value>)>
+where
+    T: DeserializeOwned,
+{
+    #[derive(Deserialize)]
+    struct All<T> {
+        #[serde(flatten)]
+        value: T,
+        #[serde(flatten, deserialize_with = "compat::extra_tags::deserialize")]
+        extra: std::collections::BTreeMap<u8, Value>,
+    }
+
+    let all = from_slice::<All<T>>(slice)?;
+    Ok((all.value, all.extra))
+}
+
+/// 通过`DeserializeSeed`反序列化，可在解码过程中携带外部状态
+pub fn from_slice_seed<'a, S>(slice: &'a [u8], seed: S) -> Result<S::Value>
+where
+    S: DeserializeSeed<'a>,
+{
+    let mut deserializer = Deserializer::from_slice(slice);
+    seed.deserialize(&mut deserializer)
+}
+
 pub fn from_reader<'a, T, R: Read>(reader: R) -> Result<T>
 where
     T: Deserialize<'a>,
@@ -49,9 +146,219 @@ where
     Ok(t)
 }
 
+/// `decode_frame`默认允许的最大帧长度（10MB）
+pub const DEFAULT_MAX_FRAME_LEN: usize = 10 * 1024 * 1024;
+
+/// 从流中读取一帧并解码：4字节大端长度前缀，之后为该长度的消息体
+///
+/// 长度为0时按空消息体解码，超过`DEFAULT_MAX_FRAME_LEN`时报错
+pub fn decode_frame<R: Read, T: DeserializeOwned>(reader: &mut R) -> Result<T> {
+    decode_frame_with_limit(reader, DEFAULT_MAX_FRAME_LEN)
+}
+
+/// 同`decode_frame`，长度超过max_len时在读取消息体之前报错
+pub fn decode_frame_with_limit<R: Read, T: DeserializeOwned>(
+    reader: &mut R,
+    max_len: usize,
+) -> Result<T> {
+    let mut prefix = [0u8; 4];
+    reader.read_exact(&mut prefix)?;
+    let len = u32::from_be_bytes(prefix) as usize;
+    if len > max_len {
+        return Err(Error::Message(format!(
+            "Frame length {} exceeds max_len {}",
+            len, max_len
+        )));
+    }
+    let mut body = vec![0u8; len];
+    reader.read_exact(&mut body)?;
+    from_slice(&body)
+}
+
+/// 只解码顶层结构体中指定tag的字段，其余字段直接跳过
+pub fn from_slice_field<T>(slice: &[u8], tag: u8) -> Result<Option<T>>
+where
+    T: DeserializeOwned,
+{
+    let mut deserializer = Deserializer::from_slice(slice);
+    deserializer.deserialize_field(tag)
+}
+
 pub fn from_slice_to_value(slice: &[u8]) -> Result<std::collections::BTreeMap<u8, Value>>
 where
 {
     let mut deserializer = Deserializer::new(slice);
     deserializer.deserialize_all()
 }
+
+/// 检查数据是否为完整的JCE结构，不构造任何值，比完整解码更轻量
+pub fn validate(bytes: &[u8]) -> Result<()> {
+    let mut deserializer = Deserializer::from_slice(bytes);
+    deserializer.validate()
+}
+
+/// 检查数据是否为规范化编码：整型与字符串使用最短形式，map条目按key的编码字节严格递增
+pub fn verify_canonical(slice: &[u8]) -> bool {
+    let mut deserializer = Deserializer::new(slice);
+    deserializer.verify_canonical().is_ok()
+}
+
+#[test]
+fn test_decode_frame() -> Result<()> {
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+    struct Request {
+        #[serde(rename = "0", default)]
+        id: i32,
+        #[serde(rename = "1", default)]
+        name: String,
+    }
+
+    fn write_frame<W: std::io::Write>(writer: &mut W, body: &[u8]) -> Result<()> {
+        writer.write_all(&(body.len() as u32).to_be_bytes())?;
+        writer.write_all(body)?;
+        Ok(())
+    }
+
+    let first = Request {
+        id: 1,
+        name: "first".into(),
+    };
+    let second = Request {
+        id: 2,
+        name: "second".repeat(100),
+    };
+    let (mut reader, mut writer) = std::io::pipe()?;
+    let sender = std::thread::spawn({
+        let frames = [to_vec(&first)?, to_vec(&second)?];
+        move || -> Result<()> {
+            for frame in &frames {
+                write_frame(&mut writer, frame)?;
+            }
+            write_frame(&mut writer, &[])?;
+            write_frame(&mut writer, &[0; 64])
+        }
+    });
+
+    assert_eq!(decode_frame::<_, Request>(&mut reader)?, first);
+    assert_eq!(decode_frame::<_, Request>(&mut reader)?, second);
+    assert_eq!(decode_frame::<_, Request>(&mut reader)?, Request::default());
+    let err = decode_frame_with_limit::<_, Request>(&mut reader, 63).unwrap_err();
+    assert!(err.to_string().contains("exceeds max_len 63"), "{}", err);
+    sender.join().unwrap()?;
+    Ok(())
+}
+
+#[test]
+fn test_validate_serializable() -> Result<()> {
+    #[derive(Serialize)]
+    struct Good {
+        #[serde(rename = "0")]
+        id: i32,
+        #[serde(rename = "0x10")]
+        names: Vec<String>,
+    }
+    #[derive(Serialize)]
+    struct Bad {
+        #[serde(rename = "0")]
+        id: i32,
+        name: String,
+    }
+
+    let good = Good {
+        id: 1,
+        names: vec!["a".into()],
+    };
+    validate_serializable(&good)?;
+
+    let bad = Bad {
+        id: 1,
+        name: "a".into(),
+    };
+    let err = validate_serializable(&bad).unwrap_err();
+    assert!(err.to_string().contains("Field name name"), "{}", err);
+    assert_eq!(err.to_string(), to_vec(&bad).unwrap_err().to_string());
+    Ok(())
+}
+
+#[test]
+fn test_from_slice_all() -> Result<()> {
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Known {
+        #[serde(rename = "0")]
+        id: i32,
+        #[serde(rename = "2")]
+        name: String,
+        #[serde(rename = "4", default)]
+        missing: Option<i64>,
+    }
+    #[derive(Deserialize, Debug)]
+    struct Packet {
+        #[serde(rename = "0")]
+        id: i32,
+        #[serde(flatten, deserialize_with = "compat::extra_tags::deserialize")]
+        extra: std::collections::BTreeMap<u8, Value>,
+    }
+
+    let mut buf = Vec::new();
+    raw::write_int(&mut buf, 0, 7)?;
+    raw::write_int(&mut buf, 1, 300)?;
+    raw::write_string(&mut buf, 2, "name")?;
+    raw::write_struct_begin(&mut buf, 3)?;
+    raw::write_string(&mut buf, 0, "nested")?;
+    raw::write_struct_end(&mut buf)?;
+    raw::write_bytes(&mut buf, 20, &[1, 2])?;
+
+    let packet = from_slice::<Packet>(&buf)?;
+    assert_eq!(packet.id, 7);
+    assert_eq!(
+        packet.extra.keys().copied().collect::<Vec<_>>(),
+        [1, 2, 3, 20]
+    );
+    assert_eq!(packet.extra[&1].as_i64(), Some(300));
+
+    let (known, extra) = from_slice_all::<Known>(&buf)?;
+    assert_eq!(
+        known,
+        Known {
+            id: 7,
+            name: "name".into(),
+            missing: None,
+        }
+    );
+    assert_eq!(extra.keys().copied().collect::<Vec<_>>(), [1, 3, 20]);
+    assert_eq!(extra[&3].to_json_like_string(), r#"{"0":"nested"}"#);
+    assert_eq!(extra[&20].to_json_like_string(), r#""0102""#);
+    Ok(())
+}
+
+#[test]
+fn test_to_slice() -> Result<()> {
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Small {
+        #[serde(rename = "0")]
+        id: u16,
+        #[serde(rename = "1")]
+        name: String,
+        #[serde(rename = "2")]
+        values: Vec<i32>,
+    }
+
+    let small = Small {
+        id: 1000,
+        name: "abc".into(),
+        values: vec![1, -1, 70_000],
+    };
+    let mut buf = [0u8; 32];
+    let len = to_slice(&mut buf, &small)?;
+    assert_eq!(buf[..len], to_vec(&small)?);
+    assert_eq!(from_slice::<Small>(&buf[..len])?, small);
+
+    let mut exact = [0u8; 20];
+    assert_eq!(len, 20);
+    assert_eq!(to_slice(&mut exact, &small)?, 20);
+
+    let mut short = [0u8; 19];
+    let err = to_slice(&mut short, &small).unwrap_err();
+    assert_eq!(err.to_string(), "JCE Error: Buffer full: capacity 19 bytes");
+    Ok(())
+}
@@ -0,0 +1,102 @@
+//! 网络地址的编解码
+
+/// `SocketAddr`编码为结构体，tag固定:
+/// - 0: IPv4地址，按大端位模式写为Int4
+/// - 1: 端口
+/// - 2: IPv6地址，16字节的SimpleList（不保留flowinfo与scope_id）
+///
+/// 用法: `#[serde(rename = "1", with = "serde_jce::net::socket_addr")]`
+pub mod socket_addr {
+    use serde::de::{self, IgnoredAny, MapAccess};
+    use serde::ser::SerializeStruct;
+    use serde::{Deserializer, Serializer};
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+    const FIELDS: &[&str] = &["0", "1", "2"];
+
+    pub fn serialize<S>(addr: &SocketAddr, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut s = serializer.serialize_struct("SocketAddr", 2)?;
+        match addr.ip() {
+            IpAddr::V4(ip) => s.serialize_field("0", &(u32::from(ip) as i32))?,
+            IpAddr::V6(ip) => s.serialize_field("2", serde_bytes::Bytes::new(&ip.octets()))?,
+        }
+        s.serialize_field("1", &addr.port())?;
+        s.end()
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<SocketAddr, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_struct("SocketAddr", FIELDS, SocketAddrVisitor)
+    }
+
+    struct SocketAddrVisitor;
+
+    impl<'de> de::Visitor<'de> for SocketAddrVisitor {
+        type Value = SocketAddr;
+
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.write_str("a JCE socket address struct")
+        }
+
+        fn visit_map<A>(self, mut map: A) -> Result<SocketAddr, A::Error>
+        where
+            A: MapAccess<'de>,
+        {
+            let mut ip = None;
+            let mut port = None;
+            while let Some(tag) = map.next_key::<u8>()? {
+                match tag {
+                    0 => ip = Some(IpAddr::V4(Ipv4Addr::from(map.next_value::<i32>()? as u32))),
+                    1 => port = Some(map.next_value::<u16>()?),
+                    2 => {
+                        let bytes = map.next_value::<serde_bytes::ByteBuf>()?;
+                        let octets: [u8; 16] = bytes
+                            .as_slice()
+                            .try_into()
+                            .map_err(|_| de::Error::invalid_length(bytes.len(), &"16 bytes"))?;
+                        ip = Some(IpAddr::V6(Ipv6Addr::from(octets)));
+                    }
+                    _ => {
+                        map.next_value::<IgnoredAny>()?;
+                    }
+                }
+            }
+            let ip = ip.ok_or_else(|| de::Error::missing_field("0"))?;
+            let port = port.ok_or_else(|| de::Error::missing_field("1"))?;
+            Ok(SocketAddr::new(ip, port))
+        }
+    }
+}
+
+#[test]
+fn test_socket_addr() -> crate::Result<()> {
+    use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Endpoint {
+        #[serde(rename = "1", with = "socket_addr")]
+        addr: SocketAddr,
+        #[serde(rename = "2")]
+        weight: u32,
+    }
+
+    let v4 = Endpoint {
+        addr: SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 10), 8080)),
+        weight: 3,
+    };
+    let serialized = crate::to_vec(&v4)?;
+    assert_eq!(crate::from_slice::<Endpoint>(&serialized)?, v4);
+
+    let v6 = Endpoint {
+        addr: "[2001:db8::1]:65535".parse().unwrap(),
+        weight: 0,
+    };
+    let serialized = crate::to_vec(&v6)?;
+    assert_eq!(crate::from_slice::<Endpoint>(&serialized)?, v6);
+    Ok(())
+}
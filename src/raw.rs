@@ -0,0 +1,373 @@
+//! 底层的JCE头部与基本类型读写，可用于手工拼装/解析数据包，serde的编解码也基于这些函数
+use crate::de::Value;
+use crate::error::{Error, Result};
+use crate::read::JceInput;
+use std::io::Write;
+
+/// 解析字段名中的tag，接受十进制与`0x`前缀的十六进制（如`"10"`与`"0x0A"`）
+pub fn parse_tag(name: &str) -> Option<u8> {
+    match name.strip_prefix("0x").or_else(|| name.strip_prefix("0X")) {
+        Some(hex) => u8::from_str_radix(hex, 16).ok(),
+        None => name.parse().ok(),
+    }
+}
+
+/// 写头部，typ须为0~13的已知类型（debug构建下断言检查）
+pub fn write_header<W: Write>(writer: &mut W, tag: u8, typ: u8) -> Result<()> {
+    debug_assert!(typ <= 13, "Invalid JCE type {}", typ);
+    if tag < 15 {
+        let header = (tag << 4) | typ;
+        writer.write_all(&[header])?;
+    } else {
+        let header = (15 << 4) | typ;
+        writer.write_all(&[header, tag])?;
+    }
+    Ok(())
+}
+
+/// 以能容纳该值的最短类型写整型，0写为Zero
+pub fn write_int<W: Write>(writer: &mut W, tag: u8, v: i64) -> Result<()> {
+    match v {
+        0 => write_header(writer, tag, 12)?,
+        n if n >= i8::MIN as i64 && n <= i8::MAX as i64 => {
+            write_header(writer, tag, 0)?;
+            writer.write_all(&(n as i8).to_be_bytes())?;
+        }
+        n if n >= i16::MIN as i64 && n <= i16::MAX as i64 => {
+            write_header(writer, tag, 1)?;
+            writer.write_all(&(n as i16).to_be_bytes())?;
+        }
+        n if n >= i32::MIN as i64 && n <= i32::MAX as i64 => {
+            write_header(writer, tag, 2)?;
+            writer.write_all(&(n as i32).to_be_bytes())?;
+        }
+        _ => {
+            write_header(writer, tag, 3)?;
+            writer.write_all(&v.to_be_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+pub fn write_float<W: Write>(writer: &mut W, tag: u8, v: f32) -> Result<()> {
+    write_header(writer, tag, 4)?;
+    writer.write_all(&v.to_be_bytes())?;
+    Ok(())
+}
+
+pub fn write_double<W: Write>(writer: &mut W, tag: u8, v: f64) -> Result<()> {
+    write_header(writer, tag, 5)?;
+    writer.write_all(&v.to_be_bytes())?;
+    Ok(())
+}
+
+/// 长度不超过255时写为String1，否则为String4
+pub fn write_string<W: Write>(writer: &mut W, tag: u8, v: &str) -> Result<()> {
+    write_string_header(writer, tag, v.len())?;
+    writer.write_all(v.as_bytes())?;
+    Ok(())
+}
+
+/// 写字符串的头部与长度，随后需写入len字节的UTF-8数据；超过`u32::MAX`字节时报错，不写入任何内容
+pub fn write_string_header<W: Write>(writer: &mut W, tag: u8, len: usize) -> Result<()> {
+    if len <= 0xFF {
+        write_header(writer, tag, 6)?;
+        writer.write_all(&[len as u8])?;
+    } else {
+        let len = u32::try_from(len)
+            .map_err(|_| Error::Message(format!("string too long for JCE: {} bytes", len)))?;
+        write_header(writer, tag, 7)?;
+        writer.write_all(&len.to_be_bytes())?;
+    }
+    Ok(())
+}
+
+/// 写为元素类型为Int1的SimpleList
+pub fn write_bytes<W: Write>(writer: &mut W, tag: u8, v: &[u8]) -> Result<()> {
+    write_simple_list(writer, tag, 0, v)
+}
+
+/// 写SimpleList，element_type为声明的元素类型，标准为0（Int1）；不是合法的JCE类型时报错且不写入
+pub fn write_simple_list<W: Write>(
+    writer: &mut W,
+    tag: u8,
+    element_type: u8,
+    v: &[u8],
+) -> Result<()> {
+    if element_type > 13 {
+        return Err(Error::Message(format!(
+            "Invalid SimpleList element type {}",
+            element_type
+        )));
+    }
+    write_header(writer, tag, 13)?;
+    write_header(writer, 0, element_type)?;
+    write_int(writer, 0, v.len() as i64)?;
+    writer.write_all(v)?;
+    Ok(())
+}
+
+/// 写List头部及长度，随后需写入len个tag为0的元素
+pub fn write_list_begin<W: Write>(writer: &mut W, tag: u8, len: usize) -> Result<()> {
+    write_header(writer, tag, 9)?;
+    write_int(writer, 0, len as i64)
+}
+
+/// 写Map头部及长度，随后需写入len对tag为0的key与tag为1的value
+pub fn write_map_begin<W: Write>(writer: &mut W, tag: u8, len: usize) -> Result<()> {
+    write_header(writer, tag, 8)?;
+    write_int(writer, 0, len as i64)
+}
+
+pub fn write_struct_begin<W: Write>(writer: &mut W, tag: u8) -> Result<()> {
+    write_header(writer, tag, 10)
+}
+
+pub fn write_struct_end<W: Write>(writer: &mut W) -> Result<()> {
+    writer.write_all(&[0xB])?;
+    Ok(())
+}
+
+/// 按`Value`记录的类型原样写入，整型宽度不做压缩
+pub fn write_value<W: Write>(writer: &mut W, tag: u8, value: &Value) -> Result<()> {
+    match value {
+        Value::Byte(v) => {
+            write_header(writer, tag, 0)?;
+            writer.write_all(&[*v])?;
+        }
+        Value::Int16(v) => {
+            write_header(writer, tag, 1)?;
+            writer.write_all(&v.to_be_bytes())?;
+        }
+        Value::Int32(v) => {
+            write_header(writer, tag, 2)?;
+            writer.write_all(&v.to_be_bytes())?;
+        }
+        Value::Int64(v) => {
+            write_header(writer, tag, 3)?;
+            writer.write_all(&v.to_be_bytes())?;
+        }
+        Value::Float(v) => write_float(writer, tag, *v)?,
+        Value::Double(v) => write_double(writer, tag, *v)?,
+        Value::String(v) => write_string(writer, tag, v)?,
+        Value::Bytes(v) => write_bytes(writer, tag, v)?,
+        Value::Map(entries) => {
+            write_map_begin(writer, tag, entries.len())?;
+            for (k, v) in entries {
+                write_value(writer, 0, k)?;
+                write_value(writer, 1, v)?;
+            }
+        }
+        Value::List(items) => {
+            write_list_begin(writer, tag, items.len())?;
+            for item in items {
+                write_value(writer, 0, item)?;
+            }
+        }
+        Value::Struct(fields) => {
+            write_struct_begin(writer, tag)?;
+            write_fields(writer, fields)?;
+            write_struct_end(writer)?;
+        }
+        Value::Zero => write_header(writer, tag, 12)?,
+    }
+    Ok(())
+}
+
+/// 写结构体的字段，不含头部与结束符
+pub fn write_fields<W: Write>(
+    writer: &mut W,
+    fields: &std::collections::BTreeMap<u8, Value>,
+) -> Result<()> {
+    for (tag, v) in fields {
+        write_value(writer, *tag, v)?;
+    }
+    Ok(())
+}
+
+/// 读头部，返回(tag, type)
+pub fn read_header<'de, R: JceInput<'de>>(reader: &mut R) -> Result<(u8, u8)> {
+    let head = reader.read_byte()?;
+    let mut tag = (head & 0xF0) >> 4;
+    let typ = head & 0x0F;
+    if tag == 15 {
+        tag = reader.read_byte()?;
+    }
+    Ok((tag, typ))
+}
+
+/// 读头部，输入恰好在头部之前结束时返回`None`；扩展tag的字节缺失仍视为截断，返回错误
+pub fn read_header_or_eof<'de, R: JceInput<'de>>(reader: &mut R) -> Result<Option<(u8, u8)>> {
+    let head = match reader.read_byte() {
+        Ok(head) => head,
+        Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let mut tag = (head & 0xF0) >> 4;
+    let typ = head & 0x0F;
+    if tag == 15 {
+        tag = reader.read_byte()?;
+    }
+    Ok(Some((tag, typ)))
+}
+
+/// 按头部给出的类型读有符号整型
+pub fn read_int<'de, R: JceInput<'de>>(reader: &mut R, typ: u8) -> Result<i64> {
+    Ok(match typ {
+        12 => 0,
+        0 => i8::from_be_bytes(read_array(reader)?) as i64,
+        1 => i16::from_be_bytes(read_array(reader)?) as i64,
+        2 => i32::from_be_bytes(read_array(reader)?) as i64,
+        3 => i64::from_be_bytes(read_array(reader)?),
+        _ => return Err(Error::Message(format!("Invalid int type {}", typ))),
+    })
+}
+
+/// 按头部给出的类型读浮点数
+pub fn read_double<'de, R: JceInput<'de>>(reader: &mut R, typ: u8) -> Result<f64> {
+    Ok(match typ {
+        4 => f32::from_be_bytes(read_array(reader)?) as f64,
+        5 => f64::from_be_bytes(read_array(reader)?),
+        _ => return Err(Error::Message(format!("Invalid float type {}", typ))),
+    })
+}
+
+/// 按头部给出的类型读字符串
+pub fn read_string<'de, R: JceInput<'de>>(reader: &mut R, typ: u8) -> Result<String> {
+    let len = match typ {
+        6 => u8::from_be_bytes(read_array(reader)?) as usize,
+        7 => u32::from_be_bytes(read_array(reader)?) as usize,
+        _ => return Err(Error::Message("Not a string type".into())),
+    };
+    let buf = reader.read_bytes(len)?.into_owned();
+    String::from_utf8(buf).map_err(|_| Error::Message("Invalid UTF-8".into()))
+}
+
+/// 读SimpleList头部(type 13)之后的内容
+pub fn read_bytes<'de, R: JceInput<'de>>(reader: &mut R) -> Result<Vec<u8>> {
+    let (_, element_typ) = read_header(reader)?;
+    if element_typ != 0 {
+        return Err(simple_list_element_error(element_typ));
+    }
+    let (_, typ) = read_header(reader)?;
+    let len = read_int(reader, typ)?;
+    let len =
+        usize::try_from(len).map_err(|_| Error::Message(format!("Invalid length {}", len)))?;
+    Ok(reader.read_bytes(len)?.into_owned())
+}
+
+pub(crate) fn simple_list_element_error(typ: u8) -> Error {
+    Error::Message(format!(
+        "SimpleList element type must be 0 (Int1), found {} ({})",
+        typ,
+        crate::de::type_name(typ)
+    ))
+}
+
+fn read_array<'de, R: JceInput<'de>, const N: usize>(reader: &mut R) -> Result<[u8; N]> {
+    let mut buf = [0u8; N];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+#[test]
+fn test_raw_packet() -> Result<()> {
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    struct Inner {
+        #[serde(rename = "0")]
+        flag: u8,
+    }
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    struct Packet {
+        #[serde(rename = "0")]
+        id: i32,
+        #[serde(rename = "1")]
+        name: String,
+        #[serde(rename = "2")]
+        inner: Inner,
+        #[serde(rename = "3")]
+        list: Vec<i64>,
+        #[serde(rename = "20", with = "serde_bytes")]
+        data: Vec<u8>,
+    }
+
+    let mut buf = Vec::new();
+    write_int(&mut buf, 0, 100_000)?;
+    write_string(&mut buf, 1, "raw")?;
+    write_struct_begin(&mut buf, 2)?;
+    write_int(&mut buf, 0, 1)?;
+    write_struct_end(&mut buf)?;
+    write_list_begin(&mut buf, 3, 2)?;
+    write_int(&mut buf, 0, 5)?;
+    write_int(&mut buf, 0, i64::MAX)?;
+    write_bytes(&mut buf, 20, &[9, 8, 7])?;
+
+    let packet = crate::from_slice::<Packet>(&buf)?;
+    assert_eq!(
+        packet,
+        Packet {
+            id: 100_000,
+            name: "raw".into(),
+            inner: Inner { flag: 1 },
+            list: vec![5, i64::MAX],
+            data: vec![9, 8, 7],
+        }
+    );
+
+    let mut reader = &buf[..];
+    assert_eq!(read_header(&mut reader)?, (0, 2));
+    assert_eq!(read_int(&mut reader, 2)?, 100_000);
+    assert_eq!(read_header(&mut reader)?, (1, 6));
+    assert_eq!(read_string(&mut reader, 6)?, "raw");
+    Ok(())
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "Invalid JCE type 16")]
+fn test_write_header_invalid_type() {
+    let _ = write_header(&mut Vec::new(), 1, 16);
+}
+
+#[cfg(target_pointer_width = "64")]
+#[test]
+fn test_string_too_long() -> Result<()> {
+    let mut buf = Vec::new();
+    let err = write_string_header(&mut buf, 0, u32::MAX as usize + 1).unwrap_err();
+    assert!(
+        err.to_string().contains("string too long for JCE"),
+        "{}",
+        err
+    );
+    assert!(buf.is_empty());
+    write_string_header(&mut buf, 0, u32::MAX as usize)?;
+    assert_eq!(buf, [0x07, 0xFF, 0xFF, 0xFF, 0xFF]);
+
+    /// 只在计算长度时输出超过4GB的内容，不实际分配
+    struct Huge;
+    impl std::fmt::Display for Huge {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            let chunk = [b'a'; 1 << 16];
+            let chunk = std::str::from_utf8(&chunk).unwrap();
+            for _ in 0..=(u32::MAX as usize >> 16) {
+                f.write_str(chunk)?;
+            }
+            Ok(())
+        }
+    }
+    impl serde::Serialize for Huge {
+        fn serialize<S: serde::Serializer>(
+            &self,
+            serializer: S,
+        ) -> std::result::Result<S::Ok, S::Error> {
+            serializer.collect_str(self)
+        }
+    }
+    let err = crate::to_vec(&Huge).unwrap_err();
+    assert!(
+        err.to_string().contains("string too long for JCE"),
+        "{}",
+        err
+    );
+    Ok(())
+}
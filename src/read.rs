@@ -0,0 +1,210 @@
+use crate::error::{Error, Result};
+use std::borrow::Cow;
+use std::io::Read;
+
+/// 反序列化的输入源，切片输入可以直接借用原数据
+///
+/// 实现有两种: [`SliceReader`]（`Deserializer::from_slice`）可借用并知道剩余长度；
+/// 任意`Read`（`Deserializer::new`）按流读取，`read_bytes`总是拷贝
+pub trait JceInput<'de> {
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()>;
+
+    /// 读一个字节，头部与长度的读取都经过这里
+    fn read_byte(&mut self) -> Result<u8> {
+        let mut buf = [0u8];
+        self.read_exact(&mut buf)?;
+        Ok(buf[0])
+    }
+
+    /// 读取len字节，能借用时返回`Cow::Borrowed`
+    fn read_bytes(&mut self, len: usize) -> Result<Cow<'de, [u8]>>;
+
+    /// 跳过len字节
+    fn skip(&mut self, len: u64) -> Result<()>;
+
+    /// 剩余可读的字节数，未知时为`None`
+    fn remaining(&self) -> Option<usize> {
+        None
+    }
+
+    /// 剩余的输入，切片输入可据此向前查看而不消耗数据，流式输入为`None`
+    fn remaining_slice(&self) -> Option<&[u8]> {
+        None
+    }
+}
+
+impl<'de, R: Read> JceInput<'de> for R {
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+        Read::read_exact(self, buf)?;
+        Ok(())
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<Cow<'de, [u8]>> {
+        let mut buf = vec![0u8; len];
+        Read::read_exact(self, &mut buf)?;
+        Ok(Cow::Owned(buf))
+    }
+
+    fn skip(&mut self, len: u64) -> Result<()> {
+        std::io::copy(&mut self.by_ref().take(len), &mut std::io::sink())?;
+        Ok(())
+    }
+}
+
+/// 基于切片的输入，字节数组可零拷贝借用
+pub struct SliceReader<'a> {
+    slice: &'a [u8],
+}
+
+impl<'a> SliceReader<'a> {
+    pub fn new(slice: &'a [u8]) -> Self {
+        SliceReader { slice }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if len > self.slice.len() {
+            return Err(Error::Io(std::io::ErrorKind::UnexpectedEof.into()));
+        }
+        let (head, rest) = self.slice.split_at(len);
+        self.slice = rest;
+        Ok(head)
+    }
+}
+
+impl<'a> JceInput<'a> for SliceReader<'a> {
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+        buf.copy_from_slice(self.take(buf.len())?);
+        Ok(())
+    }
+
+    fn read_byte(&mut self) -> Result<u8> {
+        let (&byte, rest) = self
+            .slice
+            .split_first()
+            .ok_or_else(|| Error::Io(std::io::ErrorKind::UnexpectedEof.into()))?;
+        self.slice = rest;
+        Ok(byte)
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<Cow<'a, [u8]>> {
+        self.take(len).map(Cow::Borrowed)
+    }
+
+    fn skip(&mut self, len: u64) -> Result<()> {
+        let len = usize::try_from(len).unwrap_or(usize::MAX);
+        self.take(len)?;
+        Ok(())
+    }
+
+    fn remaining(&self) -> Option<usize> {
+        Some(self.slice.len())
+    }
+
+    fn remaining_slice(&self) -> Option<&[u8]> {
+        Some(self.slice)
+    }
+}
+
+/// 记录已读字节的输入包装，可回退到检查点重新读取
+pub struct Rewind<R> {
+    inner: R,
+    /// 检查点之后读到的字节
+    buf: Vec<u8>,
+    /// 下一次读取在buf中的位置，buf耗尽后从inner读取
+    pos: usize,
+}
+
+impl<R: Read> Rewind<R> {
+    pub fn new(inner: R) -> Self {
+        Rewind {
+            inner,
+            buf: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    /// 以当前位置作为检查点，丢弃之前记录的字节
+    pub fn checkpoint(&mut self) {
+        self.buf.drain(..self.pos);
+        self.pos = 0;
+    }
+
+    /// 回退到上一个检查点
+    pub fn rewind(&mut self) {
+        self.pos = 0;
+    }
+
+    /// 取回inner，回退后尚未重新读取的字节排在inner之前，不会丢失
+    pub fn into_inner(mut self) -> std::io::Chain<std::io::Cursor<Vec<u8>>, R> {
+        self.buf.drain(..self.pos);
+        std::io::Cursor::new(self.buf).chain(self.inner)
+    }
+}
+
+impl<R: Read> Read for Rewind<R> {
+    fn read(&mut self, out: &mut [u8]) -> std::io::Result<usize> {
+        if self.pos < self.buf.len() {
+            let n = out.len().min(self.buf.len() - self.pos);
+            out[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+            self.pos += n;
+            return Ok(n);
+        }
+        let n = self.inner.read(out)?;
+        self.buf.extend_from_slice(&out[..n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[test]
+fn test_slice_and_stream_input() -> Result<()> {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Packet<'a> {
+        #[serde(rename = "0")]
+        id: i64,
+        #[serde(rename = "1", borrow)]
+        name: Cow<'a, str>,
+        #[serde(rename = "2", borrow, with = "serde_bytes")]
+        data: Cow<'a, [u8]>,
+        #[serde(rename = "3")]
+        list: Vec<std::collections::BTreeMap<String, f64>>,
+    }
+
+    /// 每次最多读一个字节的流
+    struct Trickle<'a>(&'a [u8]);
+    impl Read for Trickle<'_> {
+        fn read(&mut self, out: &mut [u8]) -> std::io::Result<usize> {
+            let n = out.len().min(1);
+            Read::read(&mut self.0, &mut out[..n])
+        }
+    }
+
+    let packet = Packet {
+        id: -5,
+        name: "x".repeat(300).into(),
+        data: vec![1u8; 40].into(),
+        list: vec![[("a".to_string(), 1.5)].into()],
+    };
+    let buf = crate::to_vec(&packet)?;
+
+    let input = SliceReader::new(&buf);
+    assert_eq!(input.remaining(), Some(buf.len()));
+    let from_slice = Packet::deserialize(&mut crate::Deserializer::new(input))?;
+    assert!(matches!(from_slice.data, Cow::Borrowed(_)));
+    assert_eq!(from_slice, packet);
+
+    assert!(matches!(
+        SliceReader::new(&buf).read_bytes(2)?,
+        Cow::Borrowed(_)
+    ));
+    let mut stream = Trickle(&buf);
+    assert_eq!(JceInput::remaining(&stream), None);
+    assert!(matches!(stream.read_bytes(2)?, Cow::Owned(_)));
+
+    let from_stream: Packet = crate::from_reader(Trickle(&buf))?;
+    assert!(matches!(from_stream.data, Cow::Owned(_)));
+    assert_eq!(from_stream, packet);
+    Ok(())
+}
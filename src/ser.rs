@@ -1,22 +1,319 @@
 use crate::error::{Error, Result};
+use crate::raw;
 use serde::{Serialize, ser};
 use std::io::Write;
 
 pub struct Serializer<W> {
-    writer: W,
+    writer: TraceWriter<W>,
     next_tag: Option<u8>,
     depth: usize,
-    index: u8,
+    /// 顶层结构体没有头部，也不写结束符
+    bare_root: bool,
+    config: Config,
+    /// 按key排序（或长度未知）时缓存的map: (tag, [(key编码, value编码)])，顶层map的tag为None
+    map_entries: Option<(Option<u8>, Vec<EncodedEntry>)>,
+    /// `serialize_key`之后等待value: 缓存map时为key的编码，直接写入时为空
+    pending_key: Option<Vec<u8>>,
+    /// 开启`with_enforce_ascending_tags`时，每层结构体已写入的最后一个tag
+    last_tags: Vec<Option<u8>>,
+    /// 长度未知的列表先缓存元素，结束时回填长度: (tag, 元素个数, 元素编码)
+    seq_buffer: Option<(u8, usize, Vec<u8>)>,
+    /// 错误是否已附带写入记录，避免逐层重复附加
+    trace_reported: bool,
+    /// 当前值按位置解析，`None`写为Zero: 开启`with_positional_none`时的列表元素与map的key/value，
+    /// 以及枚举的变体内容
+    positional: bool,
+    /// 位置上下文中的`Some(0)`写为Int1，以区别于表示`None`的Zero；只作用于紧接着写入的标量
+    explicit_zero: bool,
+    /// 正在写[`crate::de::SimpleList`]，字节数组的首字节为元素类型
+    raw_simple_list: bool,
+}
+
+/// 已编码的map条目 (key, value)
+type EncodedEntry = (Vec<u8>, Vec<u8>);
+
+/// map转为结构体后的字段 (tag, value编码)
+type EncodedField<'a> = (u8, &'a [u8]);
+
+/// map条目的写入顺序
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MapOrder {
+    /// 按迭代顺序写入，`HashMap`的顺序不固定
+    #[default]
+    AsGiven,
+    /// 先缓存条目，按key的编码字节排序后写入，输出稳定
+    SortedByKey,
+}
+
+/// 整型的编码宽度
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IntWidth {
+    /// 使用能容纳该值的最短类型，0写为Zero
+    #[default]
+    Minimal,
+    /// 按Rust类型的位宽写入（u8/i8为Int1，u16/i16为Int2，以此类推），用于要求固定宽度的旧版对端；
+    /// 无符号值超出同位宽有符号范围时仍按`with_unsigned_bit_pattern`处理
+    Native,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Config {
+    map_order: MapOrder,
+    unsigned_bit_pattern: bool,
+    bool_as_int1: bool,
+    int_width: IntWidth,
+    ascending_tags: bool,
+    numeric_keys_as_struct: bool,
+    compact_enums: bool,
+    positional_none: bool,
 }
 
 impl<W: Write> Serializer<W> {
     pub fn new(writer: W) -> Self {
         Serializer {
-            writer,
+            writer: TraceWriter {
+                inner: writer,
+                recent: std::collections::VecDeque::new(),
+                capacity: 0,
+            },
             next_tag: None,
             depth: 0,
-            index: 0,
+            bare_root: false,
+            config: Config::default(),
+            map_entries: None,
+            pending_key: None,
+            last_tags: Vec::new(),
+            seq_buffer: None,
+            trace_reported: false,
+            positional: false,
+            explicit_zero: false,
+            raw_simple_list: false,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer.inner
+    }
+
+    /// 清除上一次序列化（包括出错中断时）遗留的状态，保留配置与writer
+    pub fn reset(&mut self) {
+        self.next_tag = None;
+        self.depth = 0;
+        self.bare_root = false;
+        self.map_entries = None;
+        self.pending_key = None;
+        self.last_tags.clear();
+        self.seq_buffer = None;
+        self.trace_reported = false;
+        self.positional = false;
+        self.explicit_zero = false;
+        self.raw_simple_list = false;
+        self.writer.recent.clear();
+    }
+
+    /// 换上新的writer并返回原来的，同时`reset`，可配合缓冲池复用`Vec<u8>`
+    pub fn replace_writer(&mut self, writer: W) -> W {
+        self.reset();
+        std::mem::replace(&mut self.writer.inner, writer)
+    }
+
+    /// 取出writer并换上默认值（如空的`Vec`，不分配内存）
+    pub fn take_writer(&mut self) -> W
+    where
+        W: Default,
+    {
+        self.replace_writer(W::default())
+    }
+
+    /// 调试模式: 记录最近写入的至多bytes个字节，序列化出错时以十六进制附在错误信息中
+    pub fn with_debug_trace(mut self, bytes: usize) -> Self {
+        self.writer.capacity = bytes;
+        self
+    }
+
+    /// 给嵌套值返回的错误附上最近写入的字节，每个错误只附加一次
+    fn traced<T>(&mut self, result: Result<T>) -> Result<T> {
+        match result {
+            Err(Error::Message(msg)) if self.writer.capacity > 0 && !self.trace_reported => {
+                self.trace_reported = true;
+                let hex: Vec<String> = self
+                    .writer
+                    .recent
+                    .iter()
+                    .map(|b| format!("{:02x}", b))
+                    .collect();
+                Err(Error::Message(format!(
+                    "{} (last {} bytes written: [{}])",
+                    msg,
+                    hex.len(),
+                    hex.join(" ")
+                )))
+            }
+            result => result,
+        }
+    }
+
+    /// 规范化输出：map条目按key的编码字节排序，相等的值总是得到相同的字节
+    pub fn with_canonical(mut self, canonical: bool) -> Self {
+        self.config.map_order = if canonical {
+            MapOrder::SortedByKey
+        } else {
+            MapOrder::AsGiven
+        };
+        self
+    }
+
+    pub fn with_map_order(mut self, order: MapOrder) -> Self {
+        self.config.map_order = order;
+        self
+    }
+
+    /// 无符号整型超出同位宽有符号范围时，按原位宽写入位模式（如u32::MAX写为Int4），
+    /// 默认则提升到能容纳该值的更宽类型
+    pub fn with_unsigned_bit_pattern(mut self, enabled: bool) -> Self {
+        self.config.unsigned_bit_pattern = enabled;
+        self
+    }
+
+    /// 结构体字段的tag必须严格递增，否则报错，用于检查字段声明顺序
+    pub fn with_enforce_ascending_tags(mut self, enabled: bool) -> Self {
+        self.config.ascending_tags = enabled;
+        self
+    }
+
+    pub fn with_int_width(mut self, width: IntWidth) -> Self {
+        self.config.int_width = width;
+        self
+    }
+
+    /// `bool`总是写为Int1（`false`为Int1的0而不是Zero），与Tars对bool的约定一致
+    pub fn with_bool_as_int1(mut self, enabled: bool) -> Self {
+        self.config.bool_as_int1 = enabled;
+        self
+    }
+
+    /// map的key全部为tag字符串（十进制或`0x`十六进制，如`serde_json::Value`的`{"0": 1, "1": "a"}`）时
+    /// 写为结构体，否则仍写为Map。默认所有map（包括JSON对象）都写为Map
+    pub fn with_numeric_keys_as_struct(mut self, enabled: bool) -> Self {
+        self.config.numeric_keys_as_struct = enabled;
+        self
+    }
+
+    /// 列表元素与map的key/value中的`None`写为Zero以保持个数一致，`Some(0)`则写为Int1；
+    /// 结构体字段的`None`仍不写入。解码端需开启`Deserializer::with_positional_none`。
+    /// Int1的0不是最短编码，输出不能通过`verify_canonical`。默认关闭，`None`总是不写入
+    pub fn with_positional_none(mut self, enabled: bool) -> Self {
+        self.config.positional_none = enabled;
+        self
+    }
+
+    /// 枚举只写变体序号（如Tars IDL的enum），单元变体以外的变体报错；解码时自动识别
+    pub fn with_compact_enums(mut self, enabled: bool) -> Self {
+        self.config.compact_enums = enabled;
+        self
+    }
+
+    /// 紧凑枚举模式下带内容的变体无法编码
+    fn check_compact_enum(&self, name: &str, variant: &str) -> Result<()> {
+        if self.config.compact_enums {
+            return Err(Error::Message(format!(
+                "Variant {}::{} has a payload and cannot be written as a compact enum",
+                name, variant
+            )));
+        }
+        Ok(())
+    }
+
+    /// 以相同配置把单个值编码到独立的缓冲区
+    fn encode_nested<T>(&self, tag: u8, value: &T) -> Result<Vec<u8>>
+    where
+        T: ?Sized + Serialize,
+    {
+        let mut buf = Vec::new();
+        let mut nested = Serializer::new(&mut buf);
+        nested.config = self.config;
+        nested.next_tag = Some(tag);
+        nested.positional = self.config.positional_none;
+        value.serialize(&mut nested)?;
+        Ok(buf)
+    }
+}
+
+/// 缓存的map条目的key均为tag字符串时，返回按tag排序的(tag, value编码)，否则返回None
+fn struct_fields(entries: &[EncodedEntry]) -> Result<Option<Vec<EncodedField<'_>>>> {
+    let mut fields = Vec::with_capacity(entries.len());
+    for (key, value) in entries {
+        let mut key = &key[..];
+        let (_, typ) = raw::read_header(&mut key)?;
+        if typ != 6 && typ != 7 {
+            return Ok(None);
         }
+        let Some(tag) = raw::parse_tag(&raw::read_string(&mut key, typ)?) else {
+            return Ok(None);
+        };
+        fields.push((tag, &value[..]));
+    }
+    fields.sort_by_key(|(tag, _)| *tag);
+    if let Some(w) = fields.windows(2).find(|w| w[0].0 == w[1].0) {
+        return Err(Error::Message(format!(
+            "Duplicate tag {} in map keys",
+            w[0].0
+        )));
+    }
+    Ok(Some(fields))
+}
+
+/// 输出的包装，调试模式下保留最近写入的字节
+struct TraceWriter<W> {
+    inner: W,
+    recent: std::collections::VecDeque<u8>,
+    /// 为0时不记录
+    capacity: usize,
+}
+
+impl<W: Write> Write for TraceWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        if self.capacity > 0 {
+            let written = &buf[..n];
+            let keep = &written[written.len().saturating_sub(self.capacity)..];
+            self.recent.extend(keep);
+            let overflow = self.recent.len().saturating_sub(self.capacity);
+            self.recent.drain(..overflow);
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// 只统计`Display`输出的字节数
+struct LenCounter(usize);
+
+impl std::fmt::Write for LenCounter {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+
+/// 把`Display`输出直接写入`io::Write`，保留底层的IO错误
+struct FmtWriter<'a, W> {
+    writer: &'a mut W,
+    written: usize,
+    error: Option<std::io::Error>,
+}
+
+impl<W: Write> std::fmt::Write for FmtWriter<'_, W> {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        self.writer.write_all(s.as_bytes()).map_err(|e| {
+            self.error = Some(e);
+            std::fmt::Error
+        })?;
+        self.written += s.len();
+        Ok(())
     }
 }
 
@@ -31,9 +328,17 @@ impl<W: Write> ser::Serializer for &mut Serializer<W> {
     type SerializeTuple = Self;
     type SerializeTupleStruct = ser::Impossible<(), Self::Error>;
     type SerializeTupleVariant = ser::Impossible<(), Self::Error>;
-    type SerializeStructVariant = ser::Impossible<(), Self::Error>;
+    type SerializeStructVariant = Self;
+
+    /// JCE是二进制格式，`Uuid`等类型据此选择紧凑的编码
+    fn is_human_readable(&self) -> bool {
+        false
+    }
 
     fn serialize_bool(self, v: bool) -> Result<()> {
+        if self.config.bool_as_int1 {
+            self.explicit_zero = true;
+        }
         if !v {
             self.write_number(0)
         } else {
@@ -42,49 +347,58 @@ impl<W: Write> ser::Serializer for &mut Serializer<W> {
     }
 
     fn serialize_i8(self, v: i8) -> Result<()> {
-        self.write_number(v as i64)
+        self.write_signed(v as i64, 8)
     }
 
     fn serialize_i16(self, v: i16) -> Result<()> {
-        self.write_number(v as i64)
+        self.write_signed(v as i64, 16)
     }
 
     fn serialize_i32(self, v: i32) -> Result<()> {
-        self.write_number(v as i64)
+        self.write_signed(v as i64, 32)
     }
 
     fn serialize_i64(self, v: i64) -> Result<()> {
+        self.write_signed(v, 64)
+    }
+
+    /// JCE没有128位整型，能放入i64的值按最短形式写入，否则报错
+    fn serialize_i128(self, v: i128) -> Result<()> {
+        let v = i64::try_from(v)
+            .map_err(|_| Error::Message(format!("i128 value {} does not fit in Int8", v)))?;
+        self.write_number(v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<()> {
+        let v = i64::try_from(v)
+            .map_err(|_| Error::Message(format!("u128 value {} does not fit in Int8", v)))?;
         self.write_number(v)
     }
 
     fn serialize_u8(self, v: u8) -> Result<()> {
-        self.write_number(v as i64)
+        self.write_unsigned(v as u64, 8)
     }
 
     fn serialize_u16(self, v: u16) -> Result<()> {
-        self.write_number(v as i64)
+        self.write_unsigned(v as u64, 16)
     }
 
     fn serialize_u32(self, v: u32) -> Result<()> {
-        self.write_number(v as i64)
+        self.write_unsigned(v as u64, 32)
     }
 
     fn serialize_u64(self, v: u64) -> Result<()> {
-        self.write_number(v as i64)
+        self.write_unsigned(v, 64)
     }
 
     fn serialize_f32(self, v: f32) -> Result<()> {
         let tag = self.next_tag.take().unwrap_or(0);
-        self.write_head(tag, 0x4)?;
-        self.writer.write_all(&v.to_be_bytes())?;
-        Ok(())
+        raw::write_float(&mut self.writer, tag, v)
     }
 
     fn serialize_f64(self, v: f64) -> Result<()> {
         let tag = self.next_tag.take().unwrap_or(0);
-        self.write_head(tag, 0x5)?;
-        self.writer.write_all(&v.to_be_bytes())?;
-        Ok(())
+        raw::write_double(&mut self.writer, tag, v)
     }
 
     fn serialize_char(self, v: char) -> Result<()> {
@@ -93,89 +407,169 @@ impl<W: Write> ser::Serializer for &mut Serializer<W> {
     }
 
     fn serialize_str(self, v: &str) -> Result<()> {
-        let len = v.len();
         let tag = self.next_tag.take().unwrap_or(0);
-        if len <= 0xFF {
-            self.write_head(tag, 0x6)?;
-            self.writer.write_all(&[len as u8])?;
-            self.writer.write_all(v.as_bytes())?;
-            Ok(())
-        } else {
-            self.write_head(tag, 0x7)?;
-            self.writer.write_all(&(len as u32).to_be_bytes())?;
-            self.writer.write_all(v.as_bytes())?;
-            Ok(())
-        }
+        raw::write_string(&mut self.writer, tag, v)
     }
 
-    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
-        let len = v.len();
+    /// 先格式化一遍计算长度，写入头部后再格式化一遍直接写入writer，不分配中间的`String`，
+    /// 因此要求`Display`实现两次输出相同
+    fn collect_str<T: ?Sized + std::fmt::Display>(self, v: &T) -> Result<()> {
+        use std::fmt::Write as _;
+
+        let mut counter = LenCounter(0);
+        write!(counter, "{}", v).map_err(|_| Error::Message("Display failed".into()))?;
         let tag = self.next_tag.take().unwrap_or(0);
-        self.write_head(tag, 0x0D)?;
-        self.writer.write_all(&[0x0])?;
+        raw::write_string_header(&mut self.writer, tag, counter.0)?;
 
-        self.next_tag = Some(0);
-        self.write_number(len as i64)?;
-        self.writer.write_all(v)?;
+        let mut adapter = FmtWriter {
+            writer: &mut self.writer,
+            written: 0,
+            error: None,
+        };
+        let result = write!(adapter, "{}", v);
+        if let Some(e) = adapter.error {
+            return Err(Error::Io(e));
+        }
+        result.map_err(|_| Error::Message("Display failed".into()))?;
+        if adapter.written != counter.0 {
+            return Err(Error::Message(format!(
+                "Display output changed between passes ({} vs {} bytes)",
+                counter.0, adapter.written
+            )));
+        }
         Ok(())
     }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        let tag = self.next_tag.take().unwrap_or(0);
+        if std::mem::take(&mut self.raw_simple_list) {
+            let (element_type, data) = v
+                .split_first()
+                .ok_or_else(|| Error::Message("SimpleList without element type".into()))?;
+            return raw::write_simple_list(&mut self.writer, tag, *element_type, data);
+        }
+        raw::write_bytes(&mut self.writer, tag, v)
+    }
     fn serialize_seq(self, len: Option<usize>) -> Result<Self> {
+        self.explicit_zero = false;
         let tag = self.next_tag.take().unwrap_or(0);
+        let Some(len) = len else {
+            self.seq_buffer = Some((tag, 0, Vec::new()));
+            return Ok(self);
+        };
         self.write_head(tag, 0x9)?;
         self.next_tag = Some(0);
-        self.write_number(len.unwrap_or(0) as i64)?;
-        self.index = 0;
+        self.write_number(len as i64)?;
         Ok(self)
     }
+    /// `BTreeMap`按key的顺序迭代，输出是确定的；`HashMap`的迭代顺序不固定，
+    /// 需要稳定输出时使用`MapOrder::SortedByKey`（或`to_vec_canonical`）。
+    /// 长度未知时先缓存条目，结束时再写入长度
     fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap> {
-        let tag = self.next_tag.take().unwrap_or(0);
-        self.write_head(tag, 0x8)?;
+        self.explicit_zero = false;
+        let tag = self.next_tag.take();
+        if self.config.map_order == MapOrder::SortedByKey
+            || self.config.numeric_keys_as_struct
+            || len.is_none()
+        {
+            self.map_entries = Some((tag, Vec::with_capacity(len.unwrap_or(0))));
+            return Ok(self);
+        }
+        self.write_head(tag.unwrap_or(0), 0x8)?;
         self.next_tag = Some(0);
-        self.write_number(len.unwrap() as i64)?;
+        self.write_number(len.unwrap_or(0) as i64)?;
         Ok(self)
     }
+    /// 结构体以结束符收尾，不写字段个数，实际写入的字段数可以与len不同
     fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
-        self.depth += 1;
-        if let Some(tag) = self.next_tag {
-            self.write_head(tag, 0xA)?
-        }
+        self.begin_struct()?;
         Ok(self)
     }
+    /// 枚举编码为结构体: tag 0为变体序号，tag 1为变体内容，结构体变体的内容为嵌套结构体
     fn serialize_struct_variant(
         self,
-        _: &'static str,
-        _: u32,
-        _: &'static str,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
         _: usize,
     ) -> Result<Self::SerializeStructVariant> {
-        todo!()
+        self.check_compact_enum(name, variant)?;
+        self.begin_struct()?;
+        self.next_tag = Some(0);
+        self.write_number(variant_index as i64)?;
+        self.next_tag = Some(1);
+        self.begin_struct()?;
+        Ok(self)
     }
+    /// 结构体字段的`None`不写入；按位置解析的值（见`with_positional_none`）写为Zero以保持个数一致
     fn serialize_none(self) -> Result<()> {
+        if self.positional {
+            self.write_head_for_next(0xC)?;
+        }
         Ok(())
     }
     fn serialize_some<T: ?Sized + Serialize>(self, v: &T) -> Result<()> {
-        v.serialize(self)
+        self.explicit_zero = self.positional;
+        let result = v.serialize(&mut *self);
+        self.explicit_zero = false;
+        result
     }
+    /// `()`与单元结构体相同，编码为空结构体
     fn serialize_unit(self) -> Result<()> {
-        todo!()
+        self.begin_struct()?;
+        self.end_struct()
     }
+    /// 单元结构体编码为空结构体
     fn serialize_unit_struct(self, _: &'static str) -> Result<()> {
-        todo!()
+        self.begin_struct()?;
+        self.end_struct()
     }
-    fn serialize_unit_variant(self, _: &'static str, _: u32, _: &'static str) -> Result<()> {
-        todo!()
+    /// 单元变体为只有tag 0（变体序号）的结构体，紧凑枚举模式下为变体序号本身
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        variant_index: u32,
+        _: &'static str,
+    ) -> Result<()> {
+        if self.config.compact_enums {
+            return self.write_signed(variant_index as i64, 32);
+        }
+        self.begin_struct()?;
+        self.next_tag = Some(0);
+        self.write_number(variant_index as i64)?;
+        self.end_struct()
     }
-    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _: &'static str, _: &T) -> Result<()> {
-        todo!()
+    /// newtype结构体与内部值的编码相同
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        v: &T,
+    ) -> Result<()> {
+        if name == crate::de::SIMPLE_LIST_TOKEN {
+            self.raw_simple_list = true;
+            let result = v.serialize(&mut *self);
+            self.raw_simple_list = false;
+            return result;
+        }
+        v.serialize(self)
     }
+    /// newtype变体为结构体: tag 0为变体序号，tag 1为内容（`None`写为Zero），
+    /// 如`Result<T, E>`的`Ok(v)`为`{0: 0, 1: v}`，`Err(e)`为`{0: 1, 1: e}`
     fn serialize_newtype_variant<T: ?Sized + Serialize>(
         self,
-        _: &'static str,
-        _: u32,
-        _: &'static str,
-        _: &T,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        value: &T,
     ) -> Result<()> {
-        todo!()
+        self.check_compact_enum(name, variant)?;
+        self.begin_struct()?;
+        self.next_tag = Some(0);
+        self.write_number(variant_index as i64)?;
+        self.next_tag = Some(1);
+        self.positional = true;
+        value.serialize(&mut *self)?;
+        self.end_struct()
     }
     fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
         self.serialize_seq(Some(len))
@@ -189,11 +583,12 @@ impl<W: Write> ser::Serializer for &mut Serializer<W> {
     }
     fn serialize_tuple_variant(
         self,
-        _: &'static str,
+        name: &'static str,
         _: u32,
-        _: &'static str,
+        variant: &'static str,
         _: usize,
     ) -> Result<Self::SerializeTupleVariant> {
+        self.check_compact_enum(name, variant)?;
         todo!()
     }
 }
@@ -204,21 +599,29 @@ impl<W: std::io::Write> ser::SerializeStruct for &mut Serializer<W> {
     where
         T: serde::Serialize + ?Sized,
     {
-        let tag = key.parse::<u8>().map_err(|_| {
-            crate::error::Error::Message(format!("Field name {} is not a valid JCE tag", key))
-        })?;
+        self.serialize_tagged(key, value)
+    }
 
-        self.next_tag = Some(tag);
+    fn end(self) -> Result<()> {
+        self.end_struct()
+    }
+}
+
+impl<W: std::io::Write> ser::SerializeStructVariant for &mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
 
-        value.serialize(&mut **self)
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.serialize_tagged(key, value)
     }
 
     fn end(self) -> Result<()> {
-        self.depth -= 1;
-        if self.depth != 0 {
-            self.writer.write_all(&[0xB])?;
-        }
-        Ok(())
+        // 先结束变体内容，再结束外层的枚举结构体
+        self.end_struct()?;
+        self.end_struct()
     }
 }
 
@@ -230,12 +633,28 @@ impl<W: std::io::Write> ser::SerializeSeq for &mut Serializer<W> {
     where
         T: ?Sized + Serialize,
     {
-        self.next_tag = Some(self.index);
-        self.index += 1;
-        value.serialize(&mut **self)?;
-        Ok(())
+        if self.seq_buffer.is_some() {
+            let element = self.encode_nested(0, value);
+            let element = self.traced(element)?;
+            if let Some((_, count, buf)) = self.seq_buffer.as_mut() {
+                *count += 1;
+                buf.extend_from_slice(&element);
+            }
+            return Ok(());
+        }
+        // 列表元素按位置解析，tag统一为0
+        self.next_tag = Some(0);
+        self.positional = self.config.positional_none;
+        let result = value.serialize(&mut **self);
+        self.traced(result)
     }
     fn end(self) -> Result<()> {
+        if let Some((tag, count, buf)) = self.seq_buffer.take() {
+            self.write_head(tag, 0x9)?;
+            self.next_tag = Some(0);
+            self.write_number(count as i64)?;
+            self.writer.write_all(&buf)?;
+        }
         Ok(())
     }
 }
@@ -248,10 +667,11 @@ impl<W: std::io::Write> ser::SerializeTuple for &mut Serializer<W> {
     where
         T: ?Sized + Serialize,
     {
-        self.next_tag = Some(self.index);
-        self.index += 1;
-        value.serialize(&mut **self)?;
-        Ok(())
+        // 列表元素按位置解析，tag统一为0
+        self.next_tag = Some(0);
+        self.positional = self.config.positional_none;
+        let result = value.serialize(&mut **self);
+        self.traced(result)
     }
     fn end(self) -> Result<()> {
         Ok(())
@@ -267,65 +687,233 @@ impl<W: std::io::Write> ser::SerializeMap for &mut Serializer<W> {
         K: ?Sized + Serialize,
         V: ?Sized + Serialize,
     {
+        if self.pending_key.is_some() {
+            return Err(Error::Message(
+                "serialize_entry called while a map key is waiting for its value".into(),
+            ));
+        }
+        if self.map_entries.is_some() {
+            let key = self.encode_nested(0, key);
+            let key = self.traced(key)?;
+            let value = self.encode_nested(1, value);
+            let value = self.traced(value)?;
+            if let Some((_, entries)) = self.map_entries.as_mut() {
+                entries.push((key, value));
+            }
+            return Ok(());
+        }
         self.next_tag = Some(0);
-        key.serialize(&mut **self)?;
+        self.positional = self.config.positional_none;
+        let result = key.serialize(&mut **self);
+        self.traced(result)?;
         self.next_tag = Some(1);
-        value.serialize(&mut **self)?;
-        Ok(())
+        self.positional = self.config.positional_none;
+        let result = value.serialize(&mut **self);
+        self.traced(result)
     }
     fn end(self) -> Result<()> {
+        if self.pending_key.take().is_some() {
+            return Err(Error::Message("Map key without a value".into()));
+        }
+        if let Some((tag, mut entries)) = self.map_entries.take() {
+            if self.config.numeric_keys_as_struct
+                && let Some(fields) = struct_fields(&entries)?
+            {
+                self.next_tag = tag;
+                self.begin_struct()?;
+                self.next_tag = None;
+                for (field_tag, value) in fields {
+                    // 没有写出任何内容的value（如`None`）与结构体字段的`None`一样省略
+                    if value.is_empty() {
+                        continue;
+                    }
+                    // value以tag 1编码，头部只有一个字节，换成字段的tag
+                    self.write_head(field_tag, value[0] & 0x0F)?;
+                    self.writer.write_all(&value[1..])?;
+                }
+                return self.end_struct();
+            }
+            if self.config.map_order == MapOrder::SortedByKey {
+                entries.sort_by(|a, b| a.0.cmp(&b.0));
+            }
+            self.write_head(tag.unwrap_or(0), 0x8)?;
+            self.next_tag = Some(0);
+            self.write_number(entries.len() as i64)?;
+            for (key, value) in entries {
+                self.writer.write_all(&key)?;
+                self.writer.write_all(&value)?;
+            }
+        }
         Ok(())
     }
-    fn serialize_key<T>(&mut self, _key: &T) -> Result<()>
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
     where
         T: ?Sized + Serialize,
     {
+        if self.pending_key.is_some() {
+            return Err(Error::Message(
+                "serialize_key called twice without serialize_value".into(),
+            ));
+        }
+        if self.map_entries.is_some() {
+            let key = self.encode_nested(0, key);
+            self.pending_key = Some(self.traced(key)?);
+            return Ok(());
+        }
+        self.next_tag = Some(0);
+        self.positional = self.config.positional_none;
+        let result = key.serialize(&mut **self);
+        self.traced(result)?;
+        self.pending_key = Some(Vec::new());
         Ok(())
     }
-    fn serialize_value<T>(&mut self, _value: &T) -> Result<()>
+    fn serialize_value<T>(&mut self, value: &T) -> Result<()>
     where
         T: ?Sized + Serialize,
     {
-        Ok(())
+        let Some(key) = self.pending_key.take() else {
+            return Err(Error::Message(
+                "serialize_value called before serialize_key".into(),
+            ));
+        };
+        if self.map_entries.is_some() {
+            let value = self.encode_nested(1, value);
+            let value = self.traced(value)?;
+            if let Some((_, entries)) = self.map_entries.as_mut() {
+                entries.push((key, value));
+            }
+            return Ok(());
+        }
+        self.next_tag = Some(1);
+        self.positional = self.config.positional_none;
+        let result = value.serialize(&mut **self);
+        self.traced(result)
     }
 }
 
 impl<W: std::io::Write> Serializer<W> {
-    fn write_head(&mut self, tag: u8, typ: u8) -> std::io::Result<()> {
-        if tag < 15 {
-            let header = (tag << 4) | typ;
-            self.writer.write_all(&[header])?;
-        } else {
-            let header = (15 << 4) | typ;
-            self.writer.write_all(&[header, tag])?;
+    fn begin_struct(&mut self) -> Result<()> {
+        // `Some`包裹的容器中，长度与字段按各自的位置规则写入
+        self.explicit_zero = false;
+        if self.depth == 0 {
+            self.bare_root = self.next_tag.is_none();
+        }
+        self.depth += 1;
+        if self.config.ascending_tags {
+            self.last_tags.push(None);
+        }
+        if let Some(tag) = self.next_tag {
+            self.write_head(tag, 0xA)?
         }
         Ok(())
     }
 
-    fn write_number(&mut self, v: i64) -> Result<()> {
-        let tag = self.next_tag.take().unwrap_or(0);
+    fn end_struct(&mut self) -> Result<()> {
+        self.depth -= 1;
+        if self.config.ascending_tags {
+            self.last_tags.pop();
+        }
+        if self.depth != 0 || !self.bare_root {
+            self.writer.write_all(&[0xB])?;
+        }
+        Ok(())
+    }
+
+    /// 以字段名作为tag写入字段
+    fn serialize_tagged<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        let tag = raw::parse_tag(key).ok_or_else(|| {
+            Error::Message(format!(
+                "Field name {} is not a valid JCE tag (expected decimal or 0x-prefixed hex)",
+                key
+            ))
+        })?;
+
+        if let Some(last) = self.last_tags.last_mut() {
+            if let Some(prev) = *last
+                && tag <= prev
+            {
+                return Err(Error::Message(format!(
+                    "Field tag {} is not greater than previous tag {}",
+                    tag, prev
+                )));
+            }
+            *last = Some(tag);
+        }
+
+        self.next_tag = Some(tag);
+        self.positional = false;
+
+        let result = value.serialize(&mut *self);
+        self.traced(result)
+    }
+
+    fn write_head(&mut self, tag: u8, typ: u8) -> Result<()> {
+        raw::write_header(&mut self.writer, tag, typ)
+    }
 
-        match v {
-            0 => self.write_head(tag, 12),
-            n if n >= i8::MIN as i64 && n <= i8::MAX as i64 => {
+    /// 写无符号整型，bits为源类型位宽
+    fn write_unsigned(&mut self, v: u64, bits: u32) -> Result<()> {
+        let signed_max = (1u64 << (bits - 1)) - 1;
+        if v <= signed_max {
+            return self.write_signed(v as i64, bits);
+        }
+        if !self.config.unsigned_bit_pattern {
+            // 默认按数值编码，u64的高位会被当作i64的符号位，位模式不变
+            return self.write_number(v as i64);
+        }
+        self.write_fixed(v as i64, bits)
+    }
+
+    /// 写有符号整型，bits为源类型位宽
+    fn write_signed(&mut self, v: i64, bits: u32) -> Result<()> {
+        match self.config.int_width {
+            IntWidth::Minimal => self.write_number(v),
+            IntWidth::Native => self.write_fixed(v, bits),
+        }
+    }
+
+    /// 按bits对应的类型写入v的低位，不做压缩
+    fn write_fixed(&mut self, v: i64, bits: u32) -> Result<()> {
+        self.explicit_zero = false;
+        let tag = self.next_tag.take().unwrap_or(0);
+        match bits {
+            8 => {
                 self.write_head(tag, 0)?;
-                self.writer.write_all(&(n as i8).to_be_bytes())
+                self.writer.write_all(&(v as u8).to_be_bytes())?;
             }
-            n if n >= i16::MIN as i64 && n <= i16::MAX as i64 => {
+            16 => {
                 self.write_head(tag, 1)?;
-                self.writer.write_all(&(n as i16).to_be_bytes())
+                self.writer.write_all(&(v as u16).to_be_bytes())?;
             }
-            n if n >= i32::MIN as i64 && n <= i32::MAX as i64 => {
+            32 => {
                 self.write_head(tag, 2)?;
-                self.writer.write_all(&(n as i32).to_be_bytes())
+                self.writer.write_all(&(v as u32).to_be_bytes())?;
             }
             _ => {
                 self.write_head(tag, 3)?;
-                self.writer.write_all(&v.to_be_bytes())
+                self.writer.write_all(&v.to_be_bytes())?;
             }
-        }?;
+        }
         Ok(())
     }
+
+    fn write_number(&mut self, v: i64) -> Result<()> {
+        let tag = self.next_tag.take().unwrap_or(0);
+        if std::mem::take(&mut self.explicit_zero) && v == 0 {
+            raw::write_header(&mut self.writer, tag, 0)?;
+            self.writer.write_all(&[0])?;
+            return Ok(());
+        }
+        raw::write_int(&mut self.writer, tag, v)
+    }
+
+    fn write_head_for_next(&mut self, typ: u8) -> Result<()> {
+        let tag = self.next_tag.take().unwrap_or(0);
+        self.write_head(tag, typ)
+    }
 }
 
 #[test]
@@ -390,3 +978,1108 @@ fn test_literal() -> Result<()> {
     println!("{:?}", serialized);
     Ok(())
 }
+
+#[test]
+fn test_canonical_map() -> Result<()> {
+    use std::collections::HashMap;
+
+    #[derive(serde::Serialize)]
+    struct Holder {
+        #[serde(rename = "0")]
+        map: HashMap<String, u32>,
+    }
+
+    let keys: Vec<String> = (0..32).map(|i| format!("key{}", i)).collect();
+    let mut forward = HashMap::new();
+    for (i, k) in keys.iter().enumerate() {
+        forward.insert(k.clone(), i as u32);
+    }
+    let mut backward = HashMap::new();
+    for (i, k) in keys.iter().enumerate().rev() {
+        backward.insert(k.clone(), i as u32);
+    }
+
+    let a = crate::to_vec_canonical(&Holder { map: forward })?;
+    let b = crate::to_vec_canonical(&Holder { map: backward })?;
+    assert_eq!(a, b);
+    assert!(crate::verify_canonical(&a));
+
+    let decoded = crate::from_slice_to_value(&a)?;
+    match &decoded[&0] {
+        crate::de::Value::Map(entries) => assert_eq!(entries.len(), 32),
+        v => panic!("unexpected {:?}", v),
+    }
+
+    // 格式错误的数据不是规范化编码: 多余的结束符、list元素与map条目的tag不对
+    for malformed in [
+        &[0x00, 0x01, 0x0B, 0x1C][..],
+        &[0x0A, 0x00, 0x01, 0x0B, 0x0B, 0x0B],
+        &[0x09, 0x00, 0x01, 0x10, 0x05],
+        &[0x08, 0x00, 0x01, 0x06, 0x01, 0x61, 0x00, 0x05],
+        &[0x09, 0x10, 0x01, 0x00, 0x05],
+    ] {
+        assert!(!crate::verify_canonical(malformed), "{:02x?}", malformed);
+    }
+    assert!(crate::verify_canonical(&[
+        0x08, 0x00, 0x01, 0x06, 0x01, 0x61, 0x10, 0x05
+    ]));
+    Ok(())
+}
+
+#[test]
+fn test_unsigned_width() -> Result<()> {
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Unsigned {
+        #[serde(rename = "0")]
+        a: u32,
+        #[serde(rename = "1")]
+        b: u16,
+    }
+    let value = Unsigned {
+        a: u32::MAX,
+        b: u16::MAX,
+    };
+
+    let widened = crate::to_vec(&value)?;
+    assert_eq!(widened[0], 0x03); // tag 0, Int8
+    assert_eq!(widened[9], 0x12); // tag 1, Int4
+
+    let mut bits = Vec::new();
+    value.serialize(&mut Serializer::new(&mut bits).with_unsigned_bit_pattern(true))?;
+    assert_eq!(bits, [0x02, 0xFF, 0xFF, 0xFF, 0xFF, 0x11, 0xFF, 0xFF]);
+    assert_eq!(crate::from_slice::<Unsigned>(&bits)?, value);
+    Ok(())
+}
+
+#[test]
+fn test_long_list() -> Result<()> {
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Long {
+        #[serde(rename = "0")]
+        list: Vec<u32>,
+        #[serde(rename = "1")]
+        nested: Vec<Vec<u8>>,
+    }
+
+    let value = Long {
+        list: (0..300).collect(),
+        nested: vec![vec![1, 2], vec![], vec![3]],
+    };
+    let serialized = crate::to_vec(&value)?;
+    assert_eq!(crate::from_slice::<Long>(&serialized)?, value);
+    Ok(())
+}
+
+#[test]
+fn test_unknown_length_seq() -> Result<()> {
+    struct Evens(u32);
+
+    impl Serialize for Evens {
+        fn serialize<S: ser::Serializer>(&self, s: S) -> std::result::Result<S::Ok, S::Error> {
+            // filter的size_hint不精确，serde以None长度调用serialize_seq
+            s.collect_seq((0..self.0).filter(|x| x % 2 == 0))
+        }
+    }
+
+    #[derive(Serialize)]
+    struct Holder {
+        #[serde(rename = "0")]
+        evens: Evens,
+        #[serde(rename = "1")]
+        after: u32,
+    }
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    struct Decoded {
+        #[serde(rename = "0")]
+        evens: Vec<u32>,
+        #[serde(rename = "1")]
+        after: u32,
+    }
+
+    let serialized = crate::to_vec(&Holder {
+        evens: Evens(10),
+        after: 7,
+    })?;
+    assert_eq!(
+        crate::from_slice::<Decoded>(&serialized)?,
+        Decoded {
+            evens: vec![0, 2, 4, 6, 8],
+            after: 7
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn test_struct_variant() -> Result<()> {
+    #[derive(Serialize, serde::Deserialize, Debug, PartialEq)]
+    enum Shape {
+        #[serde(rename = "0")]
+        Empty {},
+        #[serde(rename = "1")]
+        Point {
+            #[serde(rename = "1")]
+            x: i32,
+            #[serde(rename = "2")]
+            y: i32,
+        },
+    }
+    #[derive(Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Holder {
+        #[serde(rename = "0")]
+        shape: Shape,
+        #[serde(rename = "1")]
+        after: u8,
+    }
+
+    let holder = Holder {
+        shape: Shape::Point { x: 3, y: 400 },
+        after: 9,
+    };
+    let serialized = crate::to_vec(&holder)?;
+    assert_eq!(
+        serialized,
+        [
+            0x0A, 0x00, 0x01, // tag 0 枚举结构体, tag 0 变体序号 1
+            0x1A, 0x10, 0x03, 0x21, 0x01, 0x90, 0x0B, // tag 1 变体内容 {x, y}
+            0x0B, 0x10, 0x09,
+        ]
+    );
+    assert_eq!(crate::from_slice::<Holder>(&serialized)?, holder);
+
+    let root = crate::to_vec(&Shape::Point { x: 1, y: 2 })?;
+    assert_eq!(
+        crate::from_slice::<Shape>(&root)?,
+        Shape::Point { x: 1, y: 2 }
+    );
+    Ok(())
+}
+
+#[test]
+fn test_transparent() -> Result<()> {
+    #[derive(Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Wrapper(String);
+
+    #[derive(Serialize, serde::Deserialize, Debug, PartialEq)]
+    #[serde(transparent)]
+    struct Transparent {
+        inner: String,
+    }
+
+    #[derive(Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Holder<T> {
+        #[serde(rename = "1")]
+        v: T,
+    }
+
+    let bare = crate::to_vec(&Holder {
+        v: "id".to_string(),
+    })?;
+    let wrapped = crate::to_vec(&Holder {
+        v: Wrapper("id".into()),
+    })?;
+    let transparent = crate::to_vec(&Holder {
+        v: Transparent { inner: "id".into() },
+    })?;
+    assert_eq!(wrapped, bare);
+    assert_eq!(transparent, bare);
+
+    assert_eq!(
+        crate::from_slice::<Holder<Wrapper>>(&bare)?.v,
+        Wrapper("id".into())
+    );
+    assert_eq!(
+        crate::from_slice::<Holder<Transparent>>(&bare)?.v,
+        Transparent { inner: "id".into() }
+    );
+    Ok(())
+}
+
+#[test]
+fn test_to_writer_flush() -> Result<()> {
+    use std::io::BufWriter;
+
+    #[derive(Serialize)]
+    struct Large {
+        #[serde(rename = "0")]
+        list: Vec<u32>,
+    }
+    let value = Large {
+        list: (0..1000).collect(),
+    };
+
+    let mut writer = BufWriter::with_capacity(64 * 1024, Vec::new());
+    crate::to_writer(&mut writer, &value)?;
+    assert_eq!(writer.get_ref(), &crate::to_vec(&value)?);
+    Ok(())
+}
+
+#[test]
+fn test_empty_struct() -> Result<()> {
+    use serde::Deserialize;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Empty {}
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Skipped {
+        #[serde(rename = "0", skip_serializing_if = "Option::is_none", default)]
+        v: Option<u8>,
+    }
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Unit;
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Outer {
+        #[serde(rename = "0")]
+        empty: Empty,
+        #[serde(rename = "4")]
+        unit: Unit,
+        #[serde(rename = "1")]
+        skipped: Skipped,
+        #[serde(rename = "2")]
+        list: Vec<Empty>,
+        #[serde(rename = "3")]
+        after: u8,
+    }
+
+    assert!(crate::to_vec(&Empty {})?.is_empty());
+    assert_eq!(crate::from_slice::<Empty>(&[])?, Empty {});
+    assert!(crate::to_vec(&Skipped { v: None })?.is_empty());
+    assert_eq!(crate::from_slice::<Skipped>(&[])?, Skipped { v: None });
+
+    assert!(crate::to_vec(&Unit)?.is_empty());
+    assert_eq!(crate::from_slice::<Unit>(&[])?, Unit);
+
+    let outer = Outer {
+        empty: Empty {},
+        unit: Unit,
+        skipped: Skipped { v: None },
+        list: vec![Empty {}],
+        after: 1,
+    };
+    let serialized = crate::to_vec(&outer)?;
+    assert_eq!(
+        serialized,
+        [
+            0x0A, 0x0B, 0x4A, 0x0B, 0x1A, 0x0B, 0x29, 0x00, 0x01, 0x0A, 0x0B, 0x30, 0x01
+        ]
+    );
+    assert_eq!(crate::from_slice::<Outer>(&serialized)?, outer);
+    Ok(())
+}
+
+#[test]
+fn test_collect_str() -> Result<()> {
+    use std::fmt;
+
+    struct Addr(u8, u16);
+    impl fmt::Display for Addr {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "node-{}:{}", self.0, self.1)
+        }
+    }
+    impl Serialize for Addr {
+        fn serialize<S: ser::Serializer>(
+            &self,
+            serializer: S,
+        ) -> std::result::Result<S::Ok, S::Error> {
+            serializer.collect_str(self)
+        }
+    }
+    /// 分多次写出，总长度超过255字节，需要String4
+    struct Repeat(&'static str, usize);
+    impl fmt::Display for Repeat {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            for _ in 0..self.1 {
+                f.write_str(self.0)?;
+            }
+            Ok(())
+        }
+    }
+    impl Serialize for Repeat {
+        fn serialize<S: ser::Serializer>(
+            &self,
+            serializer: S,
+        ) -> std::result::Result<S::Ok, S::Error> {
+            serializer.collect_str(self)
+        }
+    }
+    #[derive(Serialize)]
+    struct Collected {
+        #[serde(rename = "0")]
+        addr: Addr,
+        #[serde(rename = "1")]
+        long: Vec<Addr>,
+        #[serde(rename = "2")]
+        big: Repeat,
+    }
+    #[derive(Serialize)]
+    struct Plain {
+        #[serde(rename = "0")]
+        addr: String,
+        #[serde(rename = "1")]
+        long: Vec<String>,
+        #[serde(rename = "2")]
+        big: String,
+    }
+
+    let collected = Collected {
+        addr: Addr(7, 8080),
+        long: (0..40).map(|i| Addr(i, 443)).collect(),
+        big: Repeat("0123456789", 30),
+    };
+    let plain = Plain {
+        addr: collected.addr.to_string(),
+        long: collected.long.iter().map(|a| a.to_string()).collect(),
+        big: collected.big.to_string(),
+    };
+    let serialized = crate::to_vec(&collected)?;
+    assert_eq!(serialized, crate::to_vec(&plain)?);
+    // tag 2为String4，长度300
+    let big = serialized.len() - 305;
+    assert_eq!(serialized[big..big + 5], [0x27, 0x00, 0x00, 0x01, 0x2C]);
+    Ok(())
+}
+
+#[test]
+fn test_nested_bytes() -> Result<()> {
+    use serde::Deserialize;
+    use serde_bytes::ByteBuf;
+    use std::collections::HashMap;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Blobs {
+        #[serde(rename = "0")]
+        list: Vec<ByteBuf>,
+        #[serde(rename = "1")]
+        map: HashMap<String, ByteBuf>,
+        #[serde(rename = "2")]
+        plain: Vec<Vec<u8>>,
+    }
+
+    let blobs = Blobs {
+        list: vec![
+            ByteBuf::from(vec![1, 2, 3]),
+            ByteBuf::new(),
+            ByteBuf::from(vec![0xFF; 300]),
+        ],
+        map: HashMap::from([
+            ("a".to_string(), ByteBuf::from(vec![9])),
+            ("b".to_string(), ByteBuf::new()),
+        ]),
+        plain: vec![vec![1, 200], vec![]],
+    };
+    let serialized = crate::to_vec(&blobs)?;
+    // 列表元素的SimpleList头部tag为0，内部的元素类型与长度各自独立成帧
+    assert_eq!(
+        &serialized[..8],
+        &[0x09, 0x00, 0x03, 0x0D, 0x00, 0x00, 0x03, 1]
+    );
+    assert_eq!(crate::from_slice::<Blobs>(&serialized)?, blobs);
+    Ok(())
+}
+
+#[test]
+fn test_debug_trace() -> Result<()> {
+    struct Broken;
+    impl Serialize for Broken {
+        fn serialize<S: ser::Serializer>(&self, _: S) -> std::result::Result<S::Ok, S::Error> {
+            Err(ser::Error::custom("broken value"))
+        }
+    }
+    #[derive(Serialize)]
+    struct Inner {
+        #[serde(rename = "0")]
+        list: Vec<u8>,
+        #[serde(rename = "1")]
+        broken: Broken,
+    }
+    #[derive(Serialize)]
+    struct Outer {
+        #[serde(rename = "0")]
+        id: u8,
+        #[serde(rename = "1")]
+        inner: Inner,
+    }
+    let value = Outer {
+        id: 7,
+        inner: Inner {
+            list: vec![1, 2],
+            broken: Broken,
+        },
+    };
+
+    let mut buf = Vec::new();
+    let mut serializer = Serializer::new(&mut buf).with_debug_trace(4);
+    let err = value.serialize(&mut serializer).unwrap_err().to_string();
+    assert!(
+        err.ends_with("broken value (last 4 bytes written: [00 01 00 02])"),
+        "{}",
+        err
+    );
+
+    // 未开启时错误信息不变
+    let err = crate::to_vec(&value).unwrap_err().to_string();
+    assert_eq!(err, "JCE Error: broken value");
+    Ok(())
+}
+
+#[test]
+fn test_map_order() -> Result<()> {
+    use std::collections::HashMap;
+
+    #[derive(Serialize)]
+    struct Holder {
+        #[serde(rename = "0")]
+        map: HashMap<u32, String>,
+    }
+
+    let encode = |map: HashMap<u32, String>| -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        let mut serializer = Serializer::new(&mut buf).with_map_order(MapOrder::SortedByKey);
+        Holder { map }.serialize(&mut serializer)?;
+        Ok(buf)
+    };
+    let first = encode((0..64).map(|i| (i * 7, i.to_string())).collect())?;
+    for _ in 0..8 {
+        // 每个HashMap的哈希种子不同，迭代顺序随之变化
+        let map: HashMap<u32, String> = (0..64).rev().map(|i| (i * 7, i.to_string())).collect();
+        assert_eq!(encode(map)?, first);
+    }
+    // key按编码字节排序: Zero(0x0C)排在Int1(0x00)之后
+    assert_eq!(&first[..6], &[0x08, 0x00, 0x40, 0x00, 0x07, 0x16]);
+    Ok(())
+}
+
+#[test]
+fn test_bool_as_int1() -> Result<()> {
+    #[derive(Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Flags {
+        #[serde(rename = "0")]
+        off: bool,
+        #[serde(rename = "1")]
+        on: bool,
+    }
+
+    let flags = Flags {
+        off: false,
+        on: true,
+    };
+    let mut default = Vec::new();
+    flags.serialize(&mut Serializer::new(&mut default))?;
+    assert_eq!(default, [0x0C, 0x10, 0x01]);
+
+    let mut int1 = Vec::new();
+    flags.serialize(&mut Serializer::new(&mut int1).with_bool_as_int1(true))?;
+    assert_eq!(int1, [0x00, 0x00, 0x10, 0x01]);
+    assert_eq!(crate::from_slice::<Flags>(&int1)?, flags);
+    Ok(())
+}
+
+#[test]
+fn test_int_width() -> Result<()> {
+    #[derive(Serialize)]
+    struct Ints {
+        #[serde(rename = "0")]
+        a: u32,
+        #[serde(rename = "1")]
+        b: i16,
+        #[serde(rename = "2")]
+        c: u8,
+        #[serde(rename = "3")]
+        d: i64,
+    }
+
+    let value = Ints {
+        a: 5,
+        b: -1,
+        c: 200,
+        d: 0,
+    };
+    let mut minimal = Vec::new();
+    value.serialize(&mut Serializer::new(&mut minimal))?;
+    assert_eq!(minimal, [0x00, 0x05, 0x10, 0xFF, 0x21, 0x00, 0xC8, 0x3C]);
+
+    let mut native = Vec::new();
+    value.serialize(&mut Serializer::new(&mut native).with_int_width(IntWidth::Native))?;
+    assert_eq!(
+        native,
+        [
+            0x02, 0, 0, 0, 5, // u32 -> Int4
+            0x11, 0xFF, 0xFF, // i16 -> Int2
+            0x21, 0x00, 0xC8, // 超出i8范围的u8仍提升为Int2
+            0x33, 0, 0, 0, 0, 0, 0, 0, 0, // 0也不写为Zero
+        ]
+    );
+    Ok(())
+}
+
+/// 按Tars编码规则（与Java/Go实现一致）手工写出的样例，覆盖每种类型的帧格式
+#[test]
+fn test_golden_vectors() -> Result<()> {
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Inner {
+        #[serde(rename = "0")]
+        zero: i32,
+    }
+
+    #[derive(Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Outer {
+        #[serde(rename = "15")]
+        a: u8,
+        #[serde(rename = "20")]
+        inner: Inner,
+    }
+
+    let mut long = vec![0x07, 0x00, 0x00, 0x01, 0x00];
+    long.extend([b'x'; 256]);
+
+    let vectors: Vec<(Vec<u8>, Vec<u8>)> = vec![
+        // Int1 / Zero / 负数
+        (crate::to_vec(&1u8)?, vec![0x00, 0x01]),
+        (crate::to_vec(&0i32)?, vec![0x0C]),
+        (crate::to_vec(&-1i64)?, vec![0x00, 0xFF]),
+        // Int2 / Int4 / Int8
+        (crate::to_vec(&300i16)?, vec![0x01, 0x01, 0x2C]),
+        (
+            crate::to_vec(&70_000i32)?,
+            vec![0x02, 0x00, 0x01, 0x11, 0x70],
+        ),
+        (
+            crate::to_vec(&(1i64 << 40))?,
+            vec![0x03, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00],
+        ),
+        // Float / Double
+        (crate::to_vec(&1.5f32)?, vec![0x04, 0x3F, 0xC0, 0x00, 0x00]),
+        (
+            crate::to_vec(&1.5f64)?,
+            vec![0x05, 0x3F, 0xF8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+        ),
+        // String1 / String4
+        (crate::to_vec(&"abc")?, vec![0x06, 0x03, b'a', b'b', b'c']),
+        (crate::to_vec(&"x".repeat(256))?, long),
+        // Map / List / SimpleList
+        (
+            crate::to_vec(&BTreeMap::from([(1, 2)]))?,
+            vec![0x08, 0x00, 0x01, 0x00, 0x01, 0x10, 0x02],
+        ),
+        (
+            crate::to_vec(&vec![1, 2])?,
+            vec![0x09, 0x00, 0x02, 0x00, 0x01, 0x00, 0x02],
+        ),
+        (
+            crate::to_vec(&serde_bytes::Bytes::new(&[1, 2]))?,
+            vec![0x0D, 0x00, 0x00, 0x02, 0x01, 0x02],
+        ),
+        // 扩展tag与嵌套结构体
+        (
+            crate::to_vec(&Outer {
+                a: 1,
+                inner: Inner { zero: 0 },
+            })?,
+            vec![0xF0, 0x0F, 0x01, 0xFA, 0x14, 0x0C, 0x0B],
+        ),
+    ];
+    for (i, (encoded, expected)) in vectors.iter().enumerate() {
+        assert_eq!(encoded, expected, "vector {}", i);
+    }
+
+    let outer = crate::from_slice::<Outer>(&vectors[vectors.len() - 1].1)?;
+    assert_eq!(
+        outer,
+        Outer {
+            a: 1,
+            inner: Inner { zero: 0 }
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn test_map_determinism() -> Result<()> {
+    use std::collections::BTreeMap;
+
+    struct Filtered<'a>(&'a BTreeMap<&'static str, u32>);
+
+    impl Serialize for Filtered<'_> {
+        fn serialize<S: ser::Serializer>(
+            &self,
+            serializer: S,
+        ) -> std::result::Result<S::Ok, S::Error> {
+            // filter之后迭代器的长度未知
+            serializer.collect_map(self.0.iter().filter(|(_, v)| **v != 0))
+        }
+    }
+
+    let map = BTreeMap::from([("b", 2), ("a", 1), ("z", 0)]);
+    let expected = [
+        0x08, 0x00, 0x03, // tag 0 map, 长度3
+        0x06, 0x01, b'a', 0x10, 0x01, //
+        0x06, 0x01, b'b', 0x10, 0x02, //
+        0x06, 0x01, b'z', 0x1C,
+    ];
+    for _ in 0..3 {
+        assert_eq!(crate::to_vec(&map)?, expected);
+    }
+
+    let filtered = crate::to_vec(&Filtered(&map))?;
+    assert_eq!(filtered[..3], [0x08, 0x00, 0x02]);
+    assert_eq!(filtered[3..], expected[3..13]);
+    Ok(())
+}
+
+#[test]
+fn test_map_key_value() -> Result<()> {
+    use ser::{SerializeMap, Serializer as _};
+    use std::collections::BTreeMap;
+
+    struct KeyValue;
+
+    impl Serialize for KeyValue {
+        fn serialize<S: ser::Serializer>(
+            &self,
+            serializer: S,
+        ) -> std::result::Result<S::Ok, S::Error> {
+            let mut map = serializer.serialize_map(Some(2))?;
+            map.serialize_key("b")?;
+            map.serialize_value(&2)?;
+            map.serialize_key("a")?;
+            map.serialize_value(&1)?;
+            map.end()
+        }
+    }
+
+    let serialized = crate::to_vec(&KeyValue)?;
+    assert_eq!(
+        serialized,
+        [
+            0x08, 0x00, 0x02, 0x06, 0x01, b'b', 0x10, 0x02, 0x06, 0x01, b'a', 0x10, 0x01
+        ]
+    );
+    let canonical = crate::to_vec_canonical(&KeyValue)?;
+    assert_eq!(
+        canonical,
+        crate::to_vec_canonical(&BTreeMap::from([("a", 1), ("b", 2)]))?
+    );
+
+    let mut buf = Vec::new();
+    let mut serializer = Serializer::new(&mut buf);
+    let mut map = (&mut serializer).serialize_map(Some(1))?;
+    assert!(map.serialize_value(&1).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_hex_tags() -> Result<()> {
+    #[derive(Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Decimal {
+        #[serde(rename = "10")]
+        a: u8,
+        #[serde(rename = "255")]
+        b: u8,
+    }
+
+    #[derive(Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Hex {
+        #[serde(rename = "0x0A")]
+        a: u8,
+        #[serde(rename = "0xff")]
+        b: u8,
+    }
+
+    let hex = crate::to_vec(&Hex { a: 1, b: 2 })?;
+    assert_eq!(hex, crate::to_vec(&Decimal { a: 1, b: 2 })?);
+    assert_eq!(hex, [0xA0, 0x01, 0xF0, 0xFF, 0x02]);
+    assert_eq!(crate::from_slice::<Hex>(&hex)?, Hex { a: 1, b: 2 });
+
+    #[derive(Serialize)]
+    struct Invalid {
+        #[serde(rename = "0x100")]
+        a: u8,
+    }
+    let err = crate::to_vec(&Invalid { a: 1 }).unwrap_err();
+    assert!(
+        err.to_string().contains("0x100 is not a valid JCE tag"),
+        "{}",
+        err
+    );
+    Ok(())
+}
+
+#[test]
+fn test_ascending_tags() -> Result<()> {
+    #[derive(Serialize)]
+    struct Inner {
+        #[serde(rename = "0")]
+        a: u8,
+        #[serde(rename = "1")]
+        b: u8,
+    }
+
+    #[derive(Serialize)]
+    struct Ordered {
+        #[serde(rename = "1")]
+        a: u8,
+        #[serde(rename = "2")]
+        inner: Inner,
+        #[serde(rename = "3")]
+        c: u8,
+    }
+
+    #[derive(Serialize)]
+    struct Misordered {
+        #[serde(rename = "2")]
+        a: u8,
+        #[serde(rename = "1")]
+        b: u8,
+    }
+
+    let ordered = Ordered {
+        a: 1,
+        inner: Inner { a: 2, b: 3 },
+        c: 4,
+    };
+    let mut buf = Vec::new();
+    ordered.serialize(&mut Serializer::new(&mut buf).with_enforce_ascending_tags(true))?;
+    assert_eq!(buf, crate::to_vec(&ordered)?);
+
+    let misordered = Misordered { a: 1, b: 2 };
+    crate::to_vec(&misordered)?;
+    let err = misordered
+        .serialize(&mut Serializer::new(&mut Vec::new()).with_enforce_ascending_tags(true))
+        .unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("Field tag 1 is not greater than previous tag 2"),
+        "{}",
+        err
+    );
+    Ok(())
+}
+
+#[test]
+fn test_pooled_buffers() -> Result<()> {
+    #[derive(Serialize)]
+    struct Packet {
+        #[serde(rename = "0")]
+        seq: u32,
+        #[serde(rename = "1")]
+        body: String,
+    }
+
+    let mut serializer = Serializer::new(Vec::with_capacity(256));
+    let first = Packet {
+        seq: 1,
+        body: "first".into(),
+    };
+    first.serialize(&mut serializer)?;
+    let sent = serializer.take_writer();
+    assert_eq!(sent, crate::to_vec(&first)?);
+
+    // 发送完成后清空并放回，第二次序列化复用同一块内存
+    let (ptr, capacity) = (sent.as_ptr(), sent.capacity());
+    let mut recycled = sent;
+    recycled.clear();
+    assert!(serializer.replace_writer(recycled).is_empty());
+
+    let second = Packet {
+        seq: 2,
+        body: "second".into(),
+    };
+    second.serialize(&mut serializer)?;
+    let sent = serializer.into_inner();
+    assert_eq!(sent, crate::to_vec(&second)?);
+    assert_eq!((sent.as_ptr(), sent.capacity()), (ptr, capacity));
+    Ok(())
+}
+
+#[test]
+fn test_i128() -> Result<()> {
+    assert_eq!(crate::to_vec(&0i128)?, [0x0C]);
+    assert_eq!(crate::to_vec(&5i128)?, [0x00, 5]);
+    assert_eq!(crate::to_vec(&5u128)?, [0x00, 5]);
+    assert_eq!(crate::to_vec(&-300i128)?, crate::to_vec(&-300i64)?);
+
+    for v in [0i128, 5, -1, i64::MIN as i128, i64::MAX as i128] {
+        assert_eq!(crate::from_slice::<i128>(&crate::to_vec(&v)?)?, v);
+    }
+    assert_eq!(crate::from_slice::<u128>(&crate::to_vec(&5u128)?)?, 5);
+
+    let err = crate::to_vec(&(i64::MAX as i128 + 1)).unwrap_err();
+    assert!(err.to_string().contains("does not fit in Int8"), "{}", err);
+    assert!(crate::to_vec(&u128::MAX).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_result_round_trip() -> Result<()> {
+    use serde::Deserialize;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Response {
+        #[serde(rename = "0")]
+        id: i32,
+        #[serde(rename = "1")]
+        result: std::result::Result<String, i32>,
+        #[serde(rename = "2")]
+        ack: std::result::Result<(), String>,
+        #[serde(rename = "3")]
+        cached: std::result::Result<Option<u8>, ()>,
+    }
+
+    let ok = Response {
+        id: 1,
+        result: Ok("done".into()),
+        ack: Ok(()),
+        cached: Ok(None),
+    };
+    let err = Response {
+        id: 2,
+        result: Err(-1),
+        ack: Err("denied".into()),
+        cached: Err(()),
+    };
+    for response in [ok, err] {
+        let buf = crate::to_vec(&response)?;
+        assert_eq!(crate::from_slice::<Response>(&buf)?, response);
+    }
+
+    // Err(-1): 结构体{0: 变体序号1, 1: -1}
+    let buf = crate::to_vec(&std::result::Result::<String, i32>::Err(-1))?;
+    assert_eq!(buf, [0x00, 1, 0x10, 0xFF]);
+    let mut buf = Vec::new();
+    raw::write_int(&mut buf, 0, 0)?;
+    raw::write_string(&mut buf, 1, "ok")?;
+    assert_eq!(
+        crate::from_slice::<std::result::Result<String, i32>>(&buf)?,
+        Ok("ok".into())
+    );
+    Ok(())
+}
+
+#[test]
+fn test_json_object_as_struct() -> Result<()> {
+    use serde::Deserialize;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Inner {
+        #[serde(rename = "0")]
+        flag: bool,
+    }
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Packet {
+        #[serde(rename = "0")]
+        id: i64,
+        #[serde(rename = "1")]
+        name: String,
+        #[serde(rename = "2")]
+        inner: Inner,
+        #[serde(rename = "10")]
+        list: Vec<u8>,
+        #[serde(rename = "20")]
+        attrs: std::collections::BTreeMap<String, String>,
+    }
+
+    let json = serde_json::json!({
+        "0": 7,
+        "1": "name",
+        "0x02": {"0": true},
+        "10": [1, 2],
+        "20": {"k": "v"},
+    });
+    let mut buf = Vec::new();
+    json.serialize(&mut Serializer::new(&mut buf).with_numeric_keys_as_struct(true))?;
+    assert_eq!(buf[..2], [0x00, 7]);
+    assert_eq!(
+        crate::from_slice::<Packet>(&buf)?,
+        Packet {
+            id: 7,
+            name: "name".into(),
+            inner: Inner { flag: true },
+            list: vec![1, 2],
+            attrs: [("k".to_string(), "v".to_string())].into(),
+        }
+    );
+
+    // 默认写为Map
+    assert_eq!(crate::to_vec(&json)?[0], 0x08);
+
+    let mut buf = Vec::new();
+    let err = serde_json::json!({"1": 1, "0x01": 2})
+        .serialize(&mut Serializer::new(&mut buf).with_numeric_keys_as_struct(true))
+        .unwrap_err();
+    assert!(err.to_string().contains("Duplicate tag 1"), "{}", err);
+
+    // 值为None的条目不写入，与结构体字段的None相同
+    #[derive(Serialize)]
+    struct Holder {
+        #[serde(rename = "0")]
+        map: std::collections::BTreeMap<String, Option<u32>>,
+    }
+    let holder = Holder {
+        map: [("0".to_string(), None), ("1".to_string(), Some(5))].into(),
+    };
+    let mut buf = Vec::new();
+    holder.serialize(&mut Serializer::new(&mut buf).with_numeric_keys_as_struct(true))?;
+    assert_eq!(buf, [0x0A, 0x10, 0x05, 0x0B]);
+    Ok(())
+}
+
+#[test]
+fn test_float_layout() -> Result<()> {
+    let floats: [(f32, [u8; 4]); 6] = [
+        (1.0, [0x3F, 0x80, 0x00, 0x00]),
+        (-0.0, [0x80, 0x00, 0x00, 0x00]),
+        (f32::from_bits(1), [0x00, 0x00, 0x00, 0x01]),
+        (f32::MIN_POSITIVE / 2.0, [0x00, 0x40, 0x00, 0x00]),
+        (f32::MAX, [0x7F, 0x7F, 0xFF, 0xFF]),
+        (f32::MIN, [0xFF, 0x7F, 0xFF, 0xFF]),
+    ];
+    for (v, bytes) in floats {
+        let buf = crate::to_vec(&v)?;
+        assert_eq!(buf[0], 0x04);
+        assert_eq!(buf[1..], bytes, "{:e}", v);
+        assert_eq!(crate::from_slice::<f32>(&buf)?.to_bits(), v.to_bits());
+    }
+
+    let doubles: [(f64, [u8; 8]); 6] = [
+        (1.0, [0x3F, 0xF0, 0, 0, 0, 0, 0, 0]),
+        (-0.0, [0x80, 0, 0, 0, 0, 0, 0, 0]),
+        (f64::from_bits(1), [0, 0, 0, 0, 0, 0, 0, 0x01]),
+        (f64::MIN_POSITIVE / 2.0, [0x00, 0x08, 0, 0, 0, 0, 0, 0]),
+        (f64::MAX, [0x7F, 0xEF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]),
+        (f64::MIN, [0xFF, 0xEF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]),
+    ];
+    for (v, bytes) in doubles {
+        let buf = crate::to_vec(&v)?;
+        assert_eq!(buf[0], 0x05);
+        assert_eq!(buf[1..], bytes, "{:e}", v);
+        assert_eq!(crate::from_slice::<f64>(&buf)?.to_bits(), v.to_bits());
+    }
+
+    // 结构体字段中的次正规数与负零
+    #[derive(Serialize, serde::Deserialize)]
+    struct Floats {
+        #[serde(rename = "0")]
+        a: f32,
+        #[serde(rename = "1")]
+        b: f64,
+    }
+    let buf = crate::to_vec(&Floats {
+        a: -f32::from_bits(1),
+        b: -0.0,
+    })?;
+    let decoded = crate::from_slice::<Floats>(&buf)?;
+    assert_eq!(decoded.a.to_bits(), (-f32::from_bits(1)).to_bits());
+    assert_eq!(decoded.b.to_bits(), (-0.0f64).to_bits());
+    Ok(())
+}
+
+#[test]
+fn test_struct_unknown_field_count() -> Result<()> {
+    use serde::Deserialize;
+    use serde::ser::SerializeStruct;
+
+    /// 字段个数在运行时才确定，声明的长度总是0
+    struct Sparse(Vec<(&'static str, i32)>);
+    impl Serialize for Sparse {
+        fn serialize<S: ser::Serializer>(
+            &self,
+            serializer: S,
+        ) -> std::result::Result<S::Ok, S::Error> {
+            let mut s = serializer.serialize_struct("Sparse", 0)?;
+            for (tag, v) in &self.0 {
+                s.serialize_field(tag, v)?;
+            }
+            s.end()
+        }
+    }
+
+    #[derive(Deserialize, Debug, PartialEq, Default)]
+    struct Dense {
+        #[serde(rename = "0", default)]
+        a: Option<i32>,
+        #[serde(rename = "1", default)]
+        b: Option<i32>,
+        #[serde(rename = "2", default)]
+        c: Option<i32>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Outer<T> {
+        #[serde(rename = "0")]
+        inner: T,
+        #[serde(rename = "1")]
+        tail: String,
+    }
+
+    for fields in [vec![], vec![("1", 5)], vec![("0", 1), ("1", 2), ("2", 300)]] {
+        let expected = Dense {
+            a: fields.iter().find(|f| f.0 == "0").map(|f| f.1),
+            b: fields.iter().find(|f| f.0 == "1").map(|f| f.1),
+            c: fields.iter().find(|f| f.0 == "2").map(|f| f.1),
+        };
+        let sparse = Sparse(fields);
+        assert_eq!(
+            crate::from_slice::<Dense>(&crate::to_vec(&sparse)?)?,
+            expected
+        );
+
+        let outer = Outer {
+            inner: sparse,
+            tail: "end".into(),
+        };
+        let decoded = crate::from_slice::<Outer<Dense>>(&crate::to_vec(&outer)?)?;
+        assert_eq!(decoded.inner, expected);
+        assert_eq!(decoded.tail, "end");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_compact_enums() -> Result<()> {
+    use serde::Deserialize;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+    enum Status {
+        Ok,
+        Busy,
+        Failed,
+    }
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Reply {
+        #[serde(rename = "0")]
+        status: Status,
+        #[serde(rename = "1")]
+        history: Vec<Status>,
+    }
+    #[derive(Serialize)]
+    enum Payload {
+        Text(String),
+    }
+
+    fn compact<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        value.serialize(&mut Serializer::new(&mut buf).with_compact_enums(true))?;
+        Ok(buf)
+    }
+
+    assert_eq!(compact(&Status::Ok)?, [0x0C]);
+    assert_eq!(compact(&Status::Failed)?, [0x00, 2]);
+    assert_eq!(crate::from_slice::<Status>(&[0x00, 2])?, Status::Failed);
+
+    let reply = Reply {
+        status: Status::Busy,
+        history: vec![Status::Ok, Status::Failed],
+    };
+    let buf = compact(&reply)?;
+    assert_eq!(buf, [0x00, 1, 0x19, 0x00, 2, 0x0C, 0x00, 2]);
+    assert_eq!(crate::from_slice::<Reply>(&buf)?, reply);
+    // 默认的结构体形式同样可以解码
+    assert_eq!(crate::from_slice::<Reply>(&crate::to_vec(&reply)?)?, reply);
+
+    let err = compact(&Payload::Text("a".into())).unwrap_err();
+    assert!(err.to_string().contains("Payload::Text"), "{}", err);
+    Ok(())
+}
@@ -0,0 +1,73 @@
+//! `Uuid`的编解码，按RFC 4122的字节顺序（大端）写为16字节的SimpleList，
+//! 用法: `#[serde(rename = "1", with = "serde_jce::uuid")]`
+use ::uuid::Uuid;
+use serde::{Deserializer, Serializer, de};
+
+pub fn serialize<S>(uuid: &Uuid, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_bytes(uuid.as_bytes())
+}
+
+pub fn deserialize<'de, D>(deserializer: D) -> Result<Uuid, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_bytes(UuidVisitor)
+}
+
+struct UuidVisitor;
+
+impl<'de> de::Visitor<'de> for UuidVisitor {
+    type Value = Uuid;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("a 16-byte SimpleList")
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Uuid, E> {
+        let bytes: [u8; 16] = v
+            .try_into()
+            .map_err(|_| E::invalid_length(v.len(), &self))?;
+        Ok(Uuid::from_bytes(bytes))
+    }
+}
+
+#[test]
+fn test_uuid() -> crate::Result<()> {
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Session {
+        #[serde(rename = "1", with = "crate::uuid")]
+        id: Uuid,
+    }
+
+    let id = Uuid::parse_str("67e55044-10b1-426f-9247-bb680e5fe0c8").unwrap();
+    let serialized = crate::to_vec(&Session { id })?;
+    // tag 1 SimpleList, Int1元素, 长度16
+    assert_eq!(&serialized[..4], &[0x1D, 0x00, 0x00, 0x10]);
+    assert_eq!(&serialized[4..], id.as_bytes());
+    assert_eq!(crate::from_slice::<Session>(&serialized)?, Session { id });
+
+    // tag 1 SimpleList, 长度4
+    let short = [0x1D, 0x00, 0x00, 0x04, 1, 2, 3, 4];
+    assert!(crate::from_slice::<Session>(&short).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_uuid_compact_serde() -> crate::Result<()> {
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Session {
+        #[serde(rename = "1")]
+        id: Uuid,
+    }
+
+    // 不使用`with`时，`Uuid`自身的serde实现因非human readable而写为16字节
+    let id = Uuid::parse_str("67e55044-10b1-426f-9247-bb680e5fe0c8").unwrap();
+    let serialized = crate::to_vec(&Session { id })?;
+    assert_eq!(&serialized[..4], &[0x1D, 0x00, 0x00, 0x10]);
+    assert_eq!(&serialized[4..], id.as_bytes());
+    assert_eq!(crate::from_slice::<Session>(&serialized)?, Session { id });
+    Ok(())
+}